@@ -0,0 +1,283 @@
+use crate::{
+    config::Tunables, context::BotContext, schedule::Intensity, stats::Stats,
+    strategy::StrategyKind, supervisor::TaskHealth, State,
+};
+use anyhow::Context;
+use axum::{
+    extract,
+    http::StatusCode,
+    routing::{get, post, put},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use im::HashMap;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+};
+use tokio::{
+    net::TcpListener,
+    sync::{futures::Notified, Notify},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// Runtime controls shared by the admin api and every account's main loop.
+///
+/// The main loops check these once per iteration. Changes wake them up right away.
+#[derive(Default)]
+pub struct Control {
+    paused: AtomicBool,
+    force_yoink: AtomicBool,
+    changed: Notify,
+    stats: RwLock<Option<Arc<Stats>>>,
+    diff: RwLock<HashMap<String, u64>>,
+    accounts: RwLock<BTreeMap<String, AccountStatus>>,
+    tasks: RwLock<BTreeMap<&'static str, TaskHealth>>,
+}
+
+/// What one account's main loop is up to.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct AccountStatus {
+    /// When our cooldown ends. In server time.
+    pub cooldown_ends_at: Option<DateTime<Utc>>,
+    /// When the impatient path will yoink no matter what the strategy says.
+    pub impatient_at: Option<DateTime<Utc>>,
+    /// Yoinks that errored in a row.
+    pub consecutive_failures: u32,
+    pub last_decision: Option<LastDecision>,
+}
+
+/// The most recent choice made for an account.
+#[derive(Clone, Debug, Serialize)]
+pub struct LastDecision {
+    pub strategy: &'static str,
+    pub decision: &'static str,
+    pub at: DateTime<Utc>,
+}
+
+/// The body of `GET /state`.
+#[derive(Serialize)]
+struct StateView {
+    paused: bool,
+    force_yoink: bool,
+    strategy: StrategyKind,
+    intensity: Intensity,
+    stats: Option<Arc<Stats>>,
+    diff: HashMap<String, u64>,
+    accounts: BTreeMap<String, AccountStatus>,
+    tasks: BTreeMap<&'static str, TaskHealth>,
+}
+
+/// The body of `GET /health`.
+#[derive(Serialize)]
+struct HealthView {
+    healthy: bool,
+    tasks: BTreeMap<&'static str, TaskHealth>,
+}
+
+impl Control {
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+        self.notify_changed();
+    }
+
+    /// Have the next account to check yoink right away, no matter what.
+    pub fn force_yoink(&self) {
+        self.force_yoink.store(true, Ordering::Relaxed);
+        self.notify_changed();
+    }
+
+    /// Wake every main loop that is waiting on [Control::changed].
+    pub fn notify_changed(&self) {
+        self.changed.notify_waiters();
+    }
+
+    /// Resolves the next time the admin pauses, resumes, forces a yoink or changes the tunables.
+    pub fn changed(&self) -> Notified<'_> {
+        self.changed.notified()
+    }
+
+    /// True once per [Control::force_yoink]. Only one account gets it.
+    pub fn take_force_yoink(&self) -> bool {
+        self.force_yoink.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn accounts(&self) -> BTreeMap<String, AccountStatus> {
+        self.accounts.read().expect("control lock poisoned").clone()
+    }
+
+    /// Keep the latest state for `GET /state`. Empty states from timeouts are ignored.
+    pub fn set_state<const N: usize>(&self, state: &State<N>) {
+        if let Some(stats) = state.stats.back() {
+            *self.stats.write().expect("control lock poisoned") = Some(stats.clone());
+            *self.diff.write().expect("control lock poisoned") = state.diff.clone();
+        }
+    }
+
+    pub fn set_schedule(
+        &self,
+        user_id: &str,
+        cooldown_ends_at: Option<DateTime<Utc>>,
+        impatient_at: DateTime<Utc>,
+    ) {
+        let mut accounts = self.accounts.write().expect("control lock poisoned");
+
+        let status = accounts.entry(user_id.to_string()).or_default();
+
+        status.cooldown_ends_at = cooldown_ends_at;
+        status.impatient_at = Some(impatient_at);
+    }
+
+    pub fn set_cooldown(&self, user_id: &str, cooldown_ends_at: Option<DateTime<Utc>>) {
+        self.accounts
+            .write()
+            .expect("control lock poisoned")
+            .entry(user_id.to_string())
+            .or_default()
+            .cooldown_ends_at = cooldown_ends_at;
+    }
+
+    /// Count a failed yoink. Returns how many have failed in a row.
+    pub fn add_failure(&self, user_id: &str) -> u32 {
+        let mut accounts = self.accounts.write().expect("control lock poisoned");
+
+        let status = accounts.entry(user_id.to_string()).or_default();
+
+        status.consecutive_failures += 1;
+
+        status.consecutive_failures
+    }
+
+    pub fn reset_failures(&self, user_id: &str) {
+        if let Some(status) = self
+            .accounts
+            .write()
+            .expect("control lock poisoned")
+            .get_mut(user_id)
+        {
+            status.consecutive_failures = 0;
+        }
+    }
+
+    pub fn set_decision(&self, user_id: &str, last_decision: LastDecision) {
+        self.accounts
+            .write()
+            .expect("control lock poisoned")
+            .entry(user_id.to_string())
+            .or_default()
+            .last_decision = Some(last_decision);
+    }
+
+    pub fn tasks(&self) -> BTreeMap<&'static str, TaskHealth> {
+        self.tasks.read().expect("control lock poisoned").clone()
+    }
+
+    pub fn set_task_health(&self, task: &'static str, health: TaskHealth) {
+        self.tasks
+            .write()
+            .expect("control lock poisoned")
+            .insert(task, health);
+    }
+
+    fn view(&self, tunables: &Tunables) -> StateView {
+        StateView {
+            paused: self.is_paused(),
+            force_yoink: self.force_yoink.load(Ordering::Relaxed),
+            strategy: tunables.strategy,
+            intensity: tunables.schedule.intensity_now(),
+            stats: self.stats.read().expect("control lock poisoned").clone(),
+            diff: self.diff.read().expect("control lock poisoned").clone(),
+            accounts: self.accounts(),
+            tasks: self.tasks(),
+        }
+    }
+}
+
+/// Serve the admin api until cancelled. There is no auth. Only listen on localhost!
+pub async fn serve(
+    addr: SocketAddr,
+    ctx: Arc<BotContext>,
+    cancellation_token: CancellationToken,
+) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/state", get(get_state))
+        .route("/health", get(get_health))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/yoink", post(yoink))
+        .route("/strategy", put(put_strategy))
+        .with_state(ctx);
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("binding the admin api to {}", addr))?;
+
+    info!(%addr, "serving the admin api");
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(cancellation_token.cancelled_owned())
+        .await
+        .context("admin api")
+}
+
+async fn get_state(extract::State(ctx): extract::State<Arc<BotContext>>) -> Json<StateView> {
+    Json(ctx.control.view(&ctx.tunables()))
+}
+
+/// 503 while any background task is crashing.
+async fn get_health(
+    extract::State(ctx): extract::State<Arc<BotContext>>,
+) -> (StatusCode, Json<HealthView>) {
+    let tasks = ctx.control.tasks();
+
+    let healthy = tasks.values().all(|x| x.is_healthy());
+
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(HealthView { healthy, tasks }))
+}
+
+async fn pause(extract::State(ctx): extract::State<Arc<BotContext>>) -> StatusCode {
+    info!("[admin] pausing");
+    ctx.control.set_paused(true);
+    StatusCode::NO_CONTENT
+}
+
+async fn resume(extract::State(ctx): extract::State<Arc<BotContext>>) -> StatusCode {
+    info!("[admin] resuming");
+    ctx.control.set_paused(false);
+    StatusCode::NO_CONTENT
+}
+
+async fn yoink(extract::State(ctx): extract::State<Arc<BotContext>>) -> StatusCode {
+    info!("[admin] forcing a yoink");
+    ctx.control.force_yoink();
+    StatusCode::ACCEPTED
+}
+
+/// Swap the strategy. This is the same as changing `tunables.strategy`, so a SIGHUP reload will replace it.
+async fn put_strategy(
+    extract::State(ctx): extract::State<Arc<BotContext>>,
+    Json(strategy): Json<StrategyKind>,
+) -> StatusCode {
+    info!(?strategy, "[admin] changing strategy");
+
+    // lasts until the next SIGHUP. the config file wins then
+    ctx.update_tunables(|tunables| tunables.strategy = strategy);
+
+    StatusCode::NO_CONTENT
+}
@@ -0,0 +1,214 @@
+use crate::stats::Stats;
+use std::time::Duration;
+
+/// One player's place on the leaderboard and how fast they are moving.
+#[derive(Clone, Debug)]
+pub struct PlayerReport {
+    pub user_id: String,
+    pub name: String,
+    /// 1 is first.
+    pub rank: usize,
+    pub total_secs: u64,
+    /// Seconds behind the player ranked above. `None` for first place.
+    pub gap_above: Option<u64>,
+    /// Seconds ahead of the player ranked below. `None` for last place.
+    pub gap_below: Option<u64>,
+    /// The fraction of the window that they held the flag. `None` without enough history.
+    pub hold_rate: Option<f64>,
+}
+
+/// A player that is catching up to another player.
+#[derive(Clone, Debug)]
+pub struct Overtake {
+    pub behind: String,
+    pub ahead: String,
+    /// When `behind` will pass `ahead` if both keep their hold rates.
+    pub eta: Duration,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub players: Vec<PlayerReport>,
+    /// Overtakes that involve at least one of our accounts. Soonest first.
+    pub overtakes: Vec<Overtake>,
+    /// How much time the hold rates cover.
+    pub window: Option<Duration>,
+}
+
+/// Turn stats history (oldest first) into a report. `is_ours` picks the accounts that overtakes are reported for.
+///
+/// The game only updates `user_times` every 30 minutes, so the rates get better with more history.
+pub fn analyze(history: &[Stats], is_ours: impl Fn(&str) -> bool) -> Report {
+    let Some(newest) = history.last() else {
+        return Default::default();
+    };
+
+    let oldest = &history[0];
+
+    let window = (newest.fetched_at - oldest.fetched_at)
+        .to_std()
+        .ok()
+        .filter(|x| !x.is_zero());
+
+    let leaderboard = newest.leaderboard();
+
+    let players = leaderboard
+        .iter()
+        .enumerate()
+        .map(|(i, (user_id, total_secs))| {
+            let hold_rate = window.map(|window| {
+                let old_secs = oldest.user_times.get(*user_id).copied().unwrap_or(0);
+
+                total_secs.saturating_sub(old_secs) as f64 / window.as_secs_f64()
+            });
+
+            PlayerReport {
+                user_id: user_id.to_string(),
+                name: newest.users.get(*user_id).unwrap_or(user_id).clone(),
+                rank: i + 1,
+                total_secs: *total_secs,
+                gap_above: i.checked_sub(1).map(|x| leaderboard[x].1 - total_secs),
+                gap_below: leaderboard.get(i + 1).map(|x| total_secs - x.1),
+                hold_rate,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut overtakes = vec![];
+
+    for behind in players.iter() {
+        for ahead in players[..behind.rank - 1].iter() {
+            if !is_ours(&behind.user_id) && !is_ours(&ahead.user_id) {
+                continue;
+            }
+
+            let (Some(behind_rate), Some(ahead_rate)) = (behind.hold_rate, ahead.hold_rate) else {
+                continue;
+            };
+
+            // seconds of holding gained on them per second
+            let closing_rate = behind_rate - ahead_rate;
+
+            if closing_rate <= 0.0 {
+                continue;
+            }
+
+            let gap = (ahead.total_secs - behind.total_secs) as f64;
+
+            // a tiny closing rate can take longer than a Duration can hold
+            let Ok(eta) = Duration::try_from_secs_f64(gap / closing_rate) else {
+                continue;
+            };
+
+            overtakes.push(Overtake {
+                behind: behind.user_id.clone(),
+                ahead: ahead.user_id.clone(),
+                eta,
+            });
+        }
+    }
+
+    overtakes.sort_by_key(|x| x.eta);
+
+    Report {
+        players,
+        overtakes,
+        window,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeDelta};
+
+    fn stats(secs_later: i64, user_times: &[(&str, u64)]) -> Stats {
+        Stats {
+            user_times: user_times
+                .iter()
+                .map(|(k, v)| (k.to_string(), *v))
+                .collect(),
+            users: [("farcaster:1".to_string(), "alice".to_string())]
+                .into_iter()
+                .collect(),
+            fetched_at: DateTime::UNIX_EPOCH + TimeDelta::seconds(secs_later),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_history_is_an_empty_report() {
+        let report = analyze(&[], |_| true);
+
+        assert!(report.players.is_empty());
+        assert!(report.overtakes.is_empty());
+        assert_eq!(report.window, None);
+    }
+
+    #[test]
+    fn one_snapshot_has_no_rates() {
+        let report = analyze(
+            &[stats(0, &[("farcaster:1", 10), ("farcaster:2", 20)])],
+            |_| true,
+        );
+
+        assert_eq!(report.window, None);
+        assert_eq!(report.players[0].user_id, "farcaster:2");
+        assert_eq!(report.players[1].name, "alice");
+        assert!(report.players.iter().all(|x| x.hold_rate.is_none()));
+        assert!(report.overtakes.is_empty());
+    }
+
+    #[test]
+    fn ranks_gaps_and_overtakes() {
+        let history = [
+            stats(
+                0,
+                &[
+                    ("farcaster:1", 2_000),
+                    ("farcaster:2", 1_000),
+                    ("farcaster:3", 500),
+                ],
+            ),
+            stats(
+                1_000,
+                &[
+                    ("farcaster:1", 2_100),
+                    ("farcaster:2", 1_500),
+                    ("farcaster:3", 600),
+                ],
+            ),
+        ];
+
+        let report = analyze(&history, |x| x == "farcaster:2");
+
+        assert_eq!(report.window, Some(Duration::from_secs(1_000)));
+
+        let ranks = report
+            .players
+            .iter()
+            .map(|x| (x.user_id.as_str(), x.rank, x.gap_above, x.gap_below))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            ranks,
+            [
+                ("farcaster:1", 1, None, Some(600)),
+                ("farcaster:2", 2, Some(600), Some(900)),
+                ("farcaster:3", 3, Some(900), None),
+            ]
+        );
+
+        assert_eq!(report.players[1].hold_rate, Some(0.5));
+
+        // farcaster:3 is falling behind us and neither of the others is ours
+        assert_eq!(report.overtakes.len(), 1);
+
+        let overtake = &report.overtakes[0];
+
+        assert_eq!(overtake.behind, "farcaster:2");
+        assert_eq!(overtake.ahead, "farcaster:1");
+        // 600 seconds behind and closing at 0.4 seconds per second
+        assert_eq!(overtake.eta, Duration::from_secs(1_500));
+    }
+}
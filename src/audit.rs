@@ -0,0 +1,163 @@
+use crate::{backend::YoinkOutcome, stats::Stats, strategy::Decision};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use im::HashMap;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::warn;
+
+/// Start a new file once the current one is this big.
+const AUDIT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// How many rotated files to keep. `decisions.jsonl.1` is the newest.
+const AUDIT_KEEP: usize = 5;
+
+/// One decision cycle. Written as one line of JSON.
+#[derive(Debug, Serialize)]
+pub struct AuditRecord {
+    pub at: DateTime<Utc>,
+    pub account: String,
+    /// Identifies the stats and diff that the decision was made from. Empty without stats.
+    pub snapshot: String,
+    pub holder: Option<String>,
+    pub strategy: &'static str,
+    #[serde(flatten)]
+    pub decision: Decision,
+    /// What happened when we yoinked. `None` if we didn't.
+    pub outcome: Option<YoinkOutcome>,
+    /// Why the yoink didn't happen or didn't finish.
+    pub error: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new(
+        account: &str,
+        stats: Option<&Stats>,
+        diff: &HashMap<String, u64>,
+        strategy: &'static str,
+        decision: Decision,
+    ) -> Self {
+        Self {
+            at: Utc::now(),
+            account: account.to_string(),
+            snapshot: stats.map(|x| snapshot_hash(x, diff)).unwrap_or_default(),
+            holder: stats.map(|x| x.flag.holder_id.clone()),
+            strategy,
+            decision,
+            outcome: None,
+            error: None,
+        }
+    }
+}
+
+/// A stable hash of a decision's inputs. Equal inputs always have equal hashes, across runs and rust releases.
+pub fn snapshot_hash(stats: &Stats, diff: &HashMap<String, u64>) -> String {
+    // hash maps don't have a stable order. sort everything into plain json first
+    let mut diff = diff.iter().collect::<Vec<_>>();
+    diff.sort();
+
+    let canonical = serde_json::to_vec(&(&stats.flag.holder_id, stats.leaderboard(), diff))
+        .expect("serializing strings and numbers can't fail");
+
+    let digest = Sha256::digest(canonical);
+
+    // the first 64 bits are plenty to tell snapshots apart
+    let prefix = u64::from_be_bytes(digest[..8].try_into().expect("sha256 is 32 bytes"));
+
+    format!("{:016x}", prefix)
+}
+
+/// Where decision records go. Records are dropped if it isn't backed by a file.
+#[derive(Default)]
+pub struct AuditLog {
+    file: Mutex<Option<AuditFile>>,
+}
+
+struct AuditFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+}
+
+impl AuditLog {
+    /// Write records to `data_dir/audit/decisions.jsonl`.
+    pub fn open(data_dir: &Path) -> anyhow::Result<Self> {
+        let dir = data_dir.join("audit");
+
+        std::fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+
+        let file = AuditFile::open(dir.join("decisions.jsonl"))?;
+
+        Ok(Self {
+            file: Mutex::new(Some(file)),
+        })
+    }
+
+    /// Write one record. Failures are logged instead of interrupting the game.
+    pub fn record(&self, record: &AuditRecord) {
+        let mut file = self.file.lock().expect("audit lock poisoned");
+
+        let Some(file) = file.as_mut() else {
+            return;
+        };
+
+        if let Err(err) = file.write(record) {
+            warn!(?err, "failed writing the audit log");
+        }
+    }
+}
+
+impl AuditFile {
+    fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("opening {}", path.display()))?;
+
+        let len = file.metadata()?.len();
+
+        Ok(Self { path, file, len })
+    }
+
+    fn write(&mut self, record: &AuditRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        if self.len > 0 && self.len + line.len() as u64 > AUDIT_MAX_BYTES {
+            self.rotate()?;
+        }
+
+        self.file.write_all(&line)?;
+        self.len += line.len() as u64;
+
+        Ok(())
+    }
+
+    /// `decisions.jsonl` becomes `decisions.jsonl.1`, `.1` becomes `.2`, and so on. The oldest is deleted.
+    fn rotate(&mut self) -> anyhow::Result<()> {
+        let rotated = |i: usize| PathBuf::from(format!("{}.{}", self.path.display(), i));
+
+        for i in (1..AUDIT_KEEP).rev() {
+            let from = rotated(i);
+
+            if from.exists() {
+                std::fs::rename(&from, rotated(i + 1))
+                    .with_context(|| format!("rotating {}", from.display()))?;
+            }
+        }
+
+        std::fs::rename(&self.path, rotated(1))
+            .with_context(|| format!("rotating {}", self.path.display()))?;
+
+        *self = Self::open(self.path.clone())?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use super::{YoinkBackend, YoinkOutcome};
+use crate::{team::Account, Config};
+use chrono::Utc;
+use std::time::Instant;
+use tracing::info;
+
+/// Log the frame action that we would have sent. Every attempt is treated as a successful yoink.
+pub struct DryRunBackend {
+    cast_hash: String,
+    user_id: String,
+}
+
+impl DryRunBackend {
+    pub fn new(config: &Config, account: &Account) -> Self {
+        Self {
+            cast_hash: config.cast_hash.clone(),
+            user_id: account.user_id.clone(),
+        }
+    }
+}
+
+impl YoinkBackend for DryRunBackend {
+    async fn yoink(&self, decided_at: Instant) -> anyhow::Result<YoinkOutcome> {
+        info!(
+            cast_hash = self.cast_hash,
+            user_id = self.user_id,
+            decision_to_response_ms = decided_at.elapsed().as_millis() as u64,
+            "[dry run] would have yoinked!"
+        );
+
+        Ok(YoinkOutcome::Yoinked { at: Utc::now() })
+    }
+}
@@ -0,0 +1,163 @@
+use super::{YoinkBackend, YoinkOutcome};
+use crate::team::Account;
+use chrono::Utc;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+/// An in-memory flag with the same rules as the real game. Every fake account plays for the same one. Nothing leaves the process.
+pub struct FakeFlag {
+    cooldown: Duration,
+    inner: Mutex<FakeFlagInner>,
+}
+
+#[derive(Default)]
+struct FakeFlagInner {
+    holder_id: Option<String>,
+    /// when each player last took the flag. cooldowns are per player
+    last_yoinks: HashMap<String, Instant>,
+    /// outcomes to return instead of playing. unknown frames can't happen otherwise
+    scripted: VecDeque<YoinkOutcome>,
+    attempts: u64,
+}
+
+impl FakeFlag {
+    pub fn new(cooldown: Duration) -> Self {
+        Self {
+            cooldown,
+            inner: Default::default(),
+        }
+    }
+
+    /// Who has the flag. `None` until someone yoinks it.
+    #[cfg(test)]
+    pub fn holder_id(&self) -> Option<String> {
+        self.inner
+            .lock()
+            .expect("fake flag lock poisoned")
+            .holder_id
+            .clone()
+    }
+
+    /// How many times any account has tried to yoink.
+    #[cfg(test)]
+    pub fn attempts(&self) -> u64 {
+        self.inner.lock().expect("fake flag lock poisoned").attempts
+    }
+
+    /// Return `outcome` for the next yoink by anyone instead of playing. Scripted outcomes are used in order.
+    #[cfg(test)]
+    pub fn script(&self, outcome: YoinkOutcome) {
+        self.inner
+            .lock()
+            .expect("fake flag lock poisoned")
+            .scripted
+            .push_back(outcome);
+    }
+
+    fn yoink(&self, user_id: &str) -> YoinkOutcome {
+        let mut inner = self.inner.lock().expect("fake flag lock poisoned");
+
+        inner.attempts += 1;
+
+        let attempts = inner.attempts;
+
+        if let Some(outcome) = inner.scripted.pop_front() {
+            debug!(attempts, user_id, ?outcome, "[fake] scripted");
+            return outcome;
+        }
+
+        let now = Instant::now();
+
+        if let Some(ready_at) = inner.last_yoinks.get(user_id).map(|x| *x + self.cooldown) {
+            if ready_at > now {
+                let retry_after = ready_at - now;
+
+                debug!(attempts, user_id, ?retry_after, "[fake] rate limited");
+
+                return YoinkOutcome::RateLimited {
+                    until: Some(Utc::now() + retry_after),
+                };
+            }
+        }
+
+        inner.holder_id = Some(user_id.to_string());
+        inner.last_yoinks.insert(user_id.to_string(), now);
+
+        debug!(attempts, user_id, "[fake] yoinked!");
+
+        YoinkOutcome::Yoinked { at: Utc::now() }
+    }
+}
+
+/// One account playing for a [FakeFlag].
+pub struct FakeBackend {
+    flag: Arc<FakeFlag>,
+    user_id: String,
+}
+
+impl FakeBackend {
+    pub fn new(flag: Arc<FakeFlag>, account: &Account) -> Self {
+        Self {
+            flag,
+            user_id: account.user_id.clone(),
+        }
+    }
+}
+
+impl YoinkBackend for FakeBackend {
+    async fn yoink(&self, _decided_at: Instant) -> anyhow::Result<YoinkOutcome> {
+        Ok(self.flag.yoink(&self.user_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accounts_share_the_flag() {
+        let flag = FakeFlag::new(Duration::from_secs(60));
+
+        assert!(matches!(flag.yoink("a"), YoinkOutcome::Yoinked { .. }));
+
+        // keeping the flag still costs a cooldown
+        assert!(matches!(
+            flag.yoink("a"),
+            YoinkOutcome::RateLimited { until: Some(_) }
+        ));
+        assert_eq!(flag.holder_id().as_deref(), Some("a"));
+
+        assert!(matches!(flag.yoink("b"), YoinkOutcome::Yoinked { .. }));
+        assert_eq!(flag.holder_id().as_deref(), Some("b"));
+
+        // a's cooldown isn't over
+        assert!(matches!(
+            flag.yoink("a"),
+            YoinkOutcome::RateLimited { until: Some(_) }
+        ));
+        assert_eq!(flag.holder_id().as_deref(), Some("b"));
+
+        assert_eq!(flag.attempts(), 4);
+    }
+
+    #[test]
+    fn scripted_outcomes_come_first() {
+        let flag = FakeFlag::new(Duration::ZERO);
+
+        let rate_limited = YoinkOutcome::RateLimited { until: None };
+        let unknown = YoinkOutcome::Unknown { image: None };
+
+        flag.script(rate_limited.clone());
+        flag.script(unknown.clone());
+
+        assert_eq!(flag.yoink("a"), rate_limited);
+        assert_eq!(flag.yoink("a"), unknown);
+        assert_eq!(flag.holder_id(), None);
+
+        assert!(matches!(flag.yoink("a"), YoinkOutcome::Yoinked { .. }));
+    }
+}
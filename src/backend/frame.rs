@@ -0,0 +1,175 @@
+use super::YoinkOutcome;
+use crate::clock::ServerClock;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::warn;
+use url::Url;
+
+/// The frame that the game sends back after a yoink.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct FrameResponse {
+    pub version: Option<String>,
+    pub title: Option<String>,
+    pub image: Option<Url>,
+    #[serde(default)]
+    pub buttons: Vec<FrameButton>,
+    // input: serde_json::Value,
+    // state: serde_json::Value,
+    // frames_url: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct FrameButton {
+    pub index: Option<u8>,
+    pub title: Option<String>,
+}
+
+/// Neynar's error body. Sent with a non-2xx status instead of a frame.
+#[derive(Debug, Deserialize)]
+pub struct NeynarError {
+    pub code: Option<String>,
+    pub message: String,
+}
+
+/// Sent when we tried too soon. <https://yoink.terminally.online/api/images/ratelimit?date=1721150535550>
+pub const RATE_LIMIT_IMAGE: &str = "/api/images/ratelimit";
+
+impl FrameResponse {
+    /// Turn the frame into a [YoinkOutcome].
+    ///
+    /// The rate limit image is the only one we know. Everything else is [YoinkOutcome::Unknown] and only the flag endpoint can say if we got it.
+    pub fn classify(
+        &self,
+        clock: &ServerClock,
+        cooldown: Duration,
+    ) -> anyhow::Result<YoinkOutcome> {
+        let Some(image) = self.image.as_ref() else {
+            return Ok(YoinkOutcome::Unknown { image: None });
+        };
+
+        let outcome = if image.path() == RATE_LIMIT_IMAGE {
+            YoinkOutcome::RateLimited {
+                until: self.rate_limit_until(image, clock, cooldown)?,
+            }
+        } else {
+            YoinkOutcome::Unknown {
+                image: Some(image.to_string()),
+            }
+        };
+
+        Ok(outcome)
+    }
+
+    /// When our cooldown ends in server time.
+    fn rate_limit_until(
+        &self,
+        image: &Url,
+        clock: &ServerClock,
+        cooldown: Duration,
+    ) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let Some((_, until)) = image.query_pairs().find(|(k, _)| k == "date") else {
+            warn!(?self, "failed to read rate limit query data");
+            return Ok(None);
+        };
+
+        // TODO: i don't think this is right. i think the ratelimit is always just giving us the current time
+        let last_yoinked_ms = until.parse::<i64>().context("parsing rate limit date")?;
+
+        let last_yoinked =
+            DateTime::from_timestamp_millis(last_yoinked_ms).context("rate limit date range")?;
+
+        // either way, the server's clock was at least this far along when it answered
+        clock.observe_server_timestamp(last_yoinked, Utc::now());
+
+        // TODO: get the rate limit time from stats or similar
+        let until = last_yoinked + cooldown;
+
+        if clock.is_past(until) {
+            // our cooldown is already over by the server's clock. the scheduler will fire right away
+            warn!(%until, estimate = ?clock.estimate(), "rate limit date is in the past");
+        } else {
+            let duration_ms = (until - clock.server_now()).num_milliseconds();
+
+            warn!(duration_ms, "we've been rate limited");
+        }
+
+        Ok(Some(until))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_secs(600);
+
+    fn frame(image: Option<&str>) -> FrameResponse {
+        FrameResponse {
+            version: None,
+            title: None,
+            image: image.map(|x| x.parse().unwrap()),
+            buttons: vec![],
+        }
+    }
+
+    #[test]
+    fn rate_limit_ends_a_cooldown_after_its_date() {
+        let clock = ServerClock::default();
+
+        let frame = frame(Some(
+            "https://yoink.terminally.online/api/images/ratelimit?date=1721150535550",
+        ));
+
+        let until = DateTime::from_timestamp_millis(1721150535550).unwrap() + COOLDOWN;
+
+        assert_eq!(
+            frame.classify(&clock, COOLDOWN).unwrap(),
+            YoinkOutcome::RateLimited { until: Some(until) }
+        );
+    }
+
+    #[test]
+    fn rate_limit_without_a_date_has_no_end() {
+        let clock = ServerClock::default();
+
+        let frame = frame(Some("https://yoink.terminally.online/api/images/ratelimit"));
+
+        assert_eq!(
+            frame.classify(&clock, COOLDOWN).unwrap(),
+            YoinkOutcome::RateLimited { until: None }
+        );
+    }
+
+    #[test]
+    fn bad_rate_limit_date_is_an_error() {
+        let clock = ServerClock::default();
+
+        let frame = frame(Some(
+            "https://yoink.terminally.online/api/images/ratelimit?date=soon",
+        ));
+
+        assert!(frame.classify(&clock, COOLDOWN).is_err());
+    }
+
+    #[test]
+    fn other_images_are_unknown() {
+        let clock = ServerClock::default();
+
+        let image = "https://yoink.terminally.online/api/images/yoink?date=1721150535550";
+
+        assert_eq!(
+            frame(Some(image)).classify(&clock, COOLDOWN).unwrap(),
+            YoinkOutcome::Unknown {
+                image: Some(image.to_string())
+            }
+        );
+        assert_eq!(
+            frame(None).classify(&clock, COOLDOWN).unwrap(),
+            YoinkOutcome::Unknown { image: None }
+        );
+    }
+}
@@ -0,0 +1,99 @@
+mod dry_run;
+mod fake;
+mod frame;
+mod neynar;
+
+use crate::{config::Config, context::BotContext, team::Account};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Instant};
+
+pub use dry_run::DryRunBackend;
+pub use fake::{FakeBackend, FakeFlag};
+pub use neynar::{
+    keep_warm, lookup_cast, lookup_signer, lookup_users, NeynarBackend, LOOKUP_USERS_MAX,
+};
+
+/// What happened when we tried to yoink the flag.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum YoinkOutcome {
+    /// We took the flag. `at` is when the game handled it in server time.
+    Yoinked { at: DateTime<Utc> },
+    /// We tried too soon. `until` is when our cooldown ends in server time. `None` if the response didn't say.
+    RateLimited { until: Option<DateTime<Utc>> },
+    /// A response that we don't know how to read. The flag endpoint didn't show us as the holder either.
+    Unknown { image: Option<String> },
+}
+
+impl YoinkOutcome {
+    /// A short name for logs and metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Yoinked { .. } => "yoinked",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::Unknown { .. } => "unknown",
+        }
+    }
+}
+
+/// A way of submitting the yoink frame action.
+///
+/// Backends only submit the action and interpret the response. Sleeping and scheduling are left to the caller.
+pub trait YoinkBackend {
+    /// Try to yoink the flag once. `decided_at` is when we chose to yoink, so that backends can measure how long it took to act on it.
+    async fn yoink(&self, decided_at: Instant) -> anyhow::Result<YoinkOutcome>;
+}
+
+/// Which backend the bot should use. Choose by setting the BACKEND environment variable.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// Submit real frame actions through Neynar.
+    #[default]
+    Neynar,
+    /// Log the frame action instead of sending it.
+    DryRun,
+    /// Play against an in-memory flag. Useful for tests and simulations.
+    Fake,
+}
+
+/// All of our backends behind one type so that the backend can be chosen at runtime.
+pub enum Backend {
+    Neynar(NeynarBackend),
+    DryRun(DryRunBackend),
+    Fake(FakeBackend),
+}
+
+impl Backend {
+    /// Build the backend chosen by `config.backend` for one account. Fake accounts all play for `fake_flag`.
+    pub fn from_config(
+        config: &Config,
+        client: Client,
+        ctx: &Arc<BotContext>,
+        fake_flag: Arc<FakeFlag>,
+        account: &Account,
+    ) -> anyhow::Result<Self> {
+        let backend = match config.backend {
+            BackendKind::Neynar => {
+                Self::Neynar(NeynarBackend::new(client, ctx.clone(), config, account)?)
+            }
+            BackendKind::DryRun => Self::DryRun(DryRunBackend::new(config, account)),
+            BackendKind::Fake => Self::Fake(FakeBackend::new(fake_flag, account)),
+        };
+
+        Ok(backend)
+    }
+}
+
+impl YoinkBackend for Backend {
+    #[tracing::instrument(skip_all)]
+    async fn yoink(&self, decided_at: Instant) -> anyhow::Result<YoinkOutcome> {
+        match self {
+            Self::Neynar(x) => x.yoink(decided_at).await,
+            Self::DryRun(x) => x.yoink(decided_at).await,
+            Self::Fake(x) => x.yoink(decided_at).await,
+        }
+    }
+}
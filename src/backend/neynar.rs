@@ -0,0 +1,318 @@
+use super::{
+    frame::{FrameResponse, NeynarError},
+    YoinkBackend, YoinkOutcome,
+};
+use crate::{
+    clock::ServerClock, context::BotContext, secret::Secret, sleep::sleep_with_cancel,
+    stats::fetch_flag, team::Account, Config,
+};
+use anyhow::Context;
+use bytes::Bytes;
+use chrono::Utc;
+use metrics::counter;
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde_json::json;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, trace, warn};
+
+/// How many times to check the flag after the frame says we yoinked.
+const CONFIRM_ATTEMPTS: u32 = 3;
+/// How long to wait between checks of the flag.
+const CONFIRM_DELAY: Duration = Duration::from_millis(500);
+
+/// How often to touch Neynar so that a yoink never has to wait for a new TLS connection.
+/// This needs to be shorter than the client's idle pool timeout.
+pub const KEEP_WARM_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Use [Neynar's](https://neynar.com/) API to yoink the flag.
+pub struct NeynarBackend {
+    client: Client,
+    /// the clock, and the cooldown for rate limits
+    ctx: Arc<BotContext>,
+    nn_api_key: Secret<String>,
+    /// The frame action is the same every time, so it is serialized once.
+    payload: Bytes,
+    user_id: String,
+}
+
+impl NeynarBackend {
+    pub fn new(
+        client: Client,
+        ctx: Arc<BotContext>,
+        config: &Config,
+        account: &Account,
+    ) -> anyhow::Result<Self> {
+        let payload = json!({
+            "action": {
+                "button": {
+                "index": 1
+                },
+                "frames_url": "https://yoink.terminally.online/",
+                "post_url": "https://yoink.terminally.online/api/yoink"
+            },
+            "cast_hash": config.cast_hash,
+            "signer_uuid": account.nn_signer_uuid.expose()
+        });
+
+        let payload = serde_json::to_vec(&payload)
+            .context("serializing frame action")?
+            .into();
+
+        Ok(Self {
+            client,
+            ctx,
+            nn_api_key: account.nn_api_key.clone(),
+            payload,
+            user_id: account.user_id.clone(),
+        })
+    }
+}
+
+impl YoinkBackend for NeynarBackend {
+    async fn yoink(&self, decided_at: Instant) -> anyhow::Result<YoinkOutcome> {
+        let sent = Utc::now();
+        let sent_at = Instant::now();
+
+        let response = self
+            .client
+            .post("https://api.neynar.com/v2/farcaster/frame/action")
+            .header("api_key", self.nn_api_key.expose().as_str())
+            .header(CONTENT_TYPE, "application/json")
+            .body(self.payload.clone())
+            .send()
+            .await?;
+
+        let received = Utc::now();
+
+        self.ctx
+            .clock
+            .observe_response(response.headers(), sent, received);
+
+        // the game handled our request about one latency before the response arrived. confirming below takes a while
+        let yoinked_at = self.ctx.clock.to_server(received) - self.ctx.clock.latency();
+
+        // in contested moments, this is the number that decides who gets the flag
+        info!(
+            decision_to_response_ms = decided_at.elapsed().as_millis() as u64,
+            request_ms = sent_at.elapsed().as_millis() as u64,
+            "frame action response"
+        );
+
+        if !response.status().is_success() {
+            let status = response.status();
+
+            let message = match response.json::<NeynarError>().await {
+                Ok(x) => format!("{}: {:?} {}", status, x.code, x.message),
+                Err(_) => status.to_string(),
+            };
+
+            anyhow::bail!("frame action failed. {}", message);
+        }
+
+        let response = response.json::<FrameResponse>().await?;
+
+        let outcome = response.classify(&self.ctx.clock, self.ctx.tunables().cooldown())?;
+
+        if !matches!(outcome, YoinkOutcome::Unknown { .. }) {
+            return Ok(outcome);
+        }
+
+        // the frame doesn't say if we won. ask the flag
+        if self.confirm_holder().await {
+            info!(?response, "yoinked!");
+
+            Ok(YoinkOutcome::Yoinked { at: yoinked_at })
+        } else {
+            counter!("yoinker_unconfirmed_yoinks_total", "account" => self.user_id.clone())
+                .increment(1);
+
+            warn!(?response, "yoink was not confirmed by the flag");
+
+            Ok(outcome)
+        }
+    }
+}
+
+impl NeynarBackend {
+    /// Poll the flag until it shows us as the holder.
+    async fn confirm_holder(&self) -> bool {
+        for attempt in 1..=CONFIRM_ATTEMPTS {
+            match fetch_flag(&self.client, &self.ctx.clock).await {
+                Ok(flag) if flag.holder_id == self.user_id => return true,
+                Ok(flag) => {
+                    trace!(attempt, holder_id = flag.holder_id, "flag not ours yet");
+                }
+                Err(err) => {
+                    warn!(attempt, ?err, "failed fetching flag");
+                }
+            }
+
+            if attempt < CONFIRM_ATTEMPTS {
+                sleep(CONFIRM_DELAY).await;
+            }
+        }
+
+        false
+    }
+}
+
+/// Touch Neynar every [KEEP_WARM_INTERVAL] so that the connection pool always has a warm connection.
+///
+/// The requests are unauthenticated and the response is ignored. Only the connection matters.
+pub async fn keep_warm(
+    client: Client,
+    clock: Arc<ServerClock>,
+    cancellation_token: CancellationToken,
+) -> anyhow::Result<()> {
+    while !cancellation_token.is_cancelled() {
+        let sent = Utc::now();
+        let sent_at = Instant::now();
+
+        match client.head("https://api.neynar.com/").send().await {
+            Ok(response) => {
+                clock.observe_response(response.headers(), sent, Utc::now());
+
+                trace!(
+                    status = %response.status(),
+                    request_ms = sent_at.elapsed().as_millis() as u64,
+                    "kept neynar warm"
+                );
+            }
+            Err(err) => {
+                warn!(?err, "failed keeping neynar warm");
+            }
+        }
+
+        sleep_with_cancel(&cancellation_token, KEEP_WARM_INTERVAL).await;
+    }
+
+    Ok(())
+}
+
+/// A Neynar managed signer.
+#[derive(Debug, serde::Deserialize)]
+pub struct Signer {
+    pub status: String,
+    /// `None` until the signer is approved.
+    pub fid: Option<u64>,
+}
+
+/// Ask Neynar about a signer. This is also how we find out which fid it belongs to.
+pub async fn lookup_signer(
+    client: &Client,
+    nn_api_key: &Secret<String>,
+    nn_signer_uuid: &Secret<String>,
+) -> anyhow::Result<Signer> {
+    let signer = client
+        .get("https://api.neynar.com/v2/farcaster/signer")
+        .header("api_key", nn_api_key.expose().as_str())
+        .query(&[("signer_uuid", nn_signer_uuid.expose().as_str())])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Signer>()
+        .await
+        .context("parsing signer")?;
+
+    if signer.status != "approved" {
+        warn!(status = signer.status, "signer is not approved");
+    }
+
+    Ok(signer)
+}
+
+/// Ask Neynar for a cast. Returns the fid of its author.
+pub async fn lookup_cast(
+    client: &Client,
+    nn_api_key: &Secret<String>,
+    cast_hash: &str,
+) -> anyhow::Result<u64> {
+    #[derive(Debug, serde::Deserialize)]
+    struct CastResponse {
+        cast: Cast,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Cast {
+        author: Author,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Author {
+        fid: u64,
+    }
+
+    let response = client
+        .get("https://api.neynar.com/v2/farcaster/cast")
+        .header("api_key", nn_api_key.expose().as_str())
+        .query(&[("identifier", cast_hash), ("type", "hash")])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<CastResponse>()
+        .await
+        .context("parsing cast")?;
+
+    Ok(response.cast.author.fid)
+}
+
+/// How many fids Neynar's bulk user lookup takes at once.
+pub const LOOKUP_USERS_MAX: usize = 100;
+
+/// A Farcaster user as seen by `viewer_fid`.
+#[derive(Debug, serde::Deserialize)]
+pub struct User {
+    pub fid: u64,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub follower_count: u64,
+    /// missing if there was no viewer
+    #[serde(default)]
+    pub viewer_context: ViewerContext,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct ViewerContext {
+    /// the viewer follows them
+    pub following: bool,
+    /// they follow the viewer
+    pub followed_by: bool,
+}
+
+/// Ask Neynar about up to [LOOKUP_USERS_MAX] users at once.
+pub async fn lookup_users(
+    client: &Client,
+    nn_api_key: &Secret<String>,
+    fids: &[u64],
+    viewer_fid: u64,
+) -> anyhow::Result<Vec<User>> {
+    #[derive(Debug, serde::Deserialize)]
+    struct UsersResponse {
+        users: Vec<User>,
+    }
+
+    let fids = fids
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let response = client
+        .get("https://api.neynar.com/v2/farcaster/user/bulk")
+        .header("api_key", nn_api_key.expose().as_str())
+        .query(&[("fids", fids), ("viewer_fid", viewer_fid.to_string())])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<UsersResponse>()
+        .await
+        .context("parsing users")?;
+
+    Ok(response.users)
+}
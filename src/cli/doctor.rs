@@ -0,0 +1,90 @@
+use crate::{
+    backend::{lookup_cast, lookup_signer, BackendKind},
+    clock::ServerClock,
+    config::Config,
+    stats::{fetch_flag, fetch_stats, stats_cache},
+    utils::https_client,
+};
+use std::fmt::Display;
+
+/// Print one line for a check. Returns true if it passed.
+fn report<T: Display>(name: &str, result: anyhow::Result<T>) -> bool {
+    match result {
+        Ok(x) => {
+            println!("ok   {}: {}", name, x);
+            true
+        }
+        Err(err) => {
+            println!("FAIL {}: {:#}", name, err);
+            false
+        }
+    }
+}
+
+/// Check everything that the bot needs before it can play.
+pub async fn doctor(config: Config) -> anyhow::Result<()> {
+    let client = https_client().await?;
+
+    let clock = ServerClock::default();
+
+    let mut healthy = true;
+
+    let flag = fetch_flag(&client, &clock)
+        .await
+        .map(|x| format!("held by {} ({})", x.holder_name, x.holder_id));
+    healthy &= report("flag endpoint", flag);
+
+    let stats = fetch_stats(
+        &stats_cache(config.tunables.stats_cache_ttl()),
+        &client,
+        &clock,
+    )
+    .await
+    .map(|x| format!("{} players", x.user_times.len()));
+    healthy &= report("stats endpoint", stats);
+
+    let skew = clock
+        .estimate()
+        .map(|x| {
+            format!(
+                "server is {}ms ahead of us (+/- {}ms)",
+                x.offset.num_milliseconds(),
+                x.uncertainty.num_milliseconds()
+            )
+        })
+        .ok_or_else(|| anyhow::anyhow!("no Date headers from the game server"));
+    healthy &= report("clock", skew);
+
+    if config.backend != BackendKind::Neynar {
+        println!("skip signers and cast hash: only the neynar backend uses them");
+    } else {
+        for (i, nn_signer_uuid) in config.nn_signer_uuid.iter().enumerate() {
+            // one shared key or one per signer
+            let nn_api_key = config.nn_api_key.get(i).unwrap_or(&config.nn_api_key[0]);
+
+            let signer = match lookup_signer(&client, nn_api_key, nn_signer_uuid).await {
+                Ok(x) if x.status != "approved" => Err(anyhow::anyhow!("status is {}", x.status)),
+                Ok(x) => match (x.fid, config.user_id.get(i)) {
+                    (None, _) => Err(anyhow::anyhow!("approved but has no fid")),
+                    (Some(fid), Some(user_id)) if *user_id != format!("farcaster:{}", fid) => Err(
+                        anyhow::anyhow!("belongs to farcaster:{} but user_id is {}", fid, user_id),
+                    ),
+                    (Some(fid), _) => Ok(format!("approved for farcaster:{}", fid)),
+                },
+                Err(err) => Err(err.context("api key or signer is invalid")),
+            };
+            healthy &= report(&format!("signer #{}", i), signer);
+        }
+
+        let cast = lookup_cast(&client, &config.nn_api_key[0], &config.cast_hash)
+            .await
+            .map(|fid| format!("{} by farcaster:{}", config.cast_hash, fid));
+        healthy &= report("cast hash", cast);
+    }
+
+    if !healthy {
+        anyhow::bail!("some checks failed");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use crate::{
+    clock::ServerClock,
+    config::Config,
+    stats::{fetch_stats, stats_cache},
+    team::Team,
+    utils::{format_duration, https_client},
+};
+use anyhow::Context;
+use std::time::Duration;
+
+/// Print the players with the most time holding the flag. Our accounts are marked with `*`.
+pub async fn leaderboard(config: Config, limit: usize) -> anyhow::Result<()> {
+    let client = https_client().await?;
+
+    let clock = ServerClock::default();
+
+    let stats = fetch_stats(
+        &stats_cache(config.tunables.stats_cache_ttl()),
+        &client,
+        &clock,
+    )
+    .await
+    .context("fetching stats")?;
+
+    // user ids can come from the signers instead of the config
+    let accounts = Team::accounts_from_config(&config, &client)
+        .await
+        .context("loading accounts")?;
+
+    let team = &accounts[0].team;
+
+    for (rank, (user_id, secs)) in stats.leaderboard().into_iter().take(limit).enumerate() {
+        let name = stats.users.get(user_id).unwrap_or(user_id);
+
+        let ours = if team.is_teammate(user_id) { " *" } else { "" };
+
+        println!(
+            "{:>4}. {:<24} {:>14}  {}{}",
+            rank + 1,
+            name,
+            format_duration(Duration::from_secs(secs)),
+            user_id,
+            ours
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+mod doctor;
+mod leaderboard;
+mod report;
+mod run;
+mod status;
+mod test_webhooks;
+mod tui;
+mod yoink_once;
+
+use crate::config::Config;
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+/// Play the yoink game on Farcaster.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// What to do. Defaults to `run`.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// Where logs should go instead of stderr.
+    pub fn log_file(&self) -> Option<&Path> {
+        match &self.command {
+            Some(Command::Tui { log_file }) => Some(log_file),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Subcommand)]
+pub enum Command {
+    /// Run the bot until cancelled.
+    #[default]
+    Run,
+    /// Yoink the flag one time and print what happened.
+    YoinkOnce {
+        /// Which account to yoink with. Defaults to the first one.
+        #[arg(long)]
+        user_id: Option<String>,
+    },
+    /// Show the current flag holder and our cooldowns.
+    Status,
+    /// Print the leaderboard.
+    Leaderboard {
+        /// How many players to show.
+        #[arg(long, default_value_t = 25)]
+        limit: usize,
+    },
+    /// Print gaps, hold rates, and who is about to pass who.
+    Report {
+        /// How many saved stats updates to look back over.
+        #[arg(long, default_value_t = 48)]
+        history: usize,
+        /// How many players to show.
+        #[arg(long, default_value_t = 25)]
+        limit: usize,
+    },
+    /// Check the api key, signers, cast hash, and connectivity.
+    Doctor,
+    /// Send a sample event to every webhook.
+    TestWebhooks,
+    /// Run the bot with a live dashboard instead of log output.
+    Tui {
+        /// Logs go here so that they don't draw over the dashboard.
+        #[arg(long, default_value = "yoinker.log")]
+        log_file: PathBuf,
+    },
+}
+
+impl Command {
+    pub async fn run(
+        self,
+        config: Config,
+        cancellation_token: CancellationToken,
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Run => run::run(config, cancellation_token).await,
+            Self::YoinkOnce { user_id } => yoink_once::yoink_once(config, user_id).await,
+            Self::Status => status::status(config).await,
+            Self::Leaderboard { limit } => leaderboard::leaderboard(config, limit).await,
+            Self::Report { history, limit } => report::report(config, history, limit).await,
+            Self::Doctor => doctor::doctor(config).await,
+            Self::TestWebhooks => test_webhooks::test_webhooks(config, cancellation_token).await,
+            Self::Tui { .. } => tui::tui(config, cancellation_token).await,
+        }
+    }
+}
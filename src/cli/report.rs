@@ -0,0 +1,127 @@
+use crate::{
+    analytics::analyze,
+    clock::ServerClock,
+    config::Config,
+    reputation::{reputation_path, Reputation},
+    stats::{fetch_stats, load_history, stats_cache},
+    team::Team,
+    utils::{format_duration, https_client},
+};
+use anyhow::Context;
+use chrono::Utc;
+use std::{collections::HashMap, time::Duration};
+use tracing::warn;
+
+/// Print gaps, hold rates, and overtake ETAs from the saved stats history and the current stats.
+pub async fn report(config: Config, history: usize, limit: usize) -> anyhow::Result<()> {
+    let client = https_client().await?;
+
+    let clock = ServerClock::default();
+
+    let mut stats_history = load_history(&config.data_dir, history)?;
+
+    match fetch_stats(
+        &stats_cache(config.tunables.stats_cache_ttl()),
+        &client,
+        &clock,
+    )
+    .await
+    {
+        Ok(stats) => {
+            if stats_history
+                .last()
+                .map_or(true, |x| x.user_times != stats.user_times)
+            {
+                stats_history.push(stats);
+            }
+        }
+        Err(err) if !stats_history.is_empty() => {
+            warn!(?err, "failed fetching stats. only using saved history");
+        }
+        Err(err) => return Err(err).context("fetching stats"),
+    }
+
+    // user ids can come from the signers instead of the config
+    let accounts = Team::accounts_from_config(&config, &client)
+        .await
+        .context("loading accounts")?;
+
+    let team = &accounts[0].team;
+
+    let reputation = Reputation::load(&reputation_path(&config.data_dir))?;
+
+    let now = Utc::now();
+
+    let report = analyze(&stats_history, |x| team.is_teammate(x));
+
+    match report.window {
+        Some(window) => println!(
+            "hold rates over the last {} ({} updates)",
+            format_duration(window),
+            stats_history.len()
+        ),
+        None => println!("not enough history for hold rates. run the bot for a while"),
+    }
+
+    println!();
+    println!(
+        "{:>4}  {:<24} {:>14} {:>14} {:>14} {:>9} {:>6}",
+        "#", "name", "time held", "gap above", "gap below", "hold rate", "rep"
+    );
+
+    let format_gap = |x: Option<u64>| {
+        x.map(|x| format_duration(Duration::from_secs(x)))
+            .unwrap_or_default()
+    };
+
+    for player in report.players.iter().take(limit) {
+        let ours = if team.is_teammate(&player.user_id) {
+            " *"
+        } else {
+            ""
+        };
+
+        // only players who have taken the flag from us have a reputation
+        let rep = reputation
+            .opponents
+            .get(&player.user_id)
+            .map(|x| format!("{:.2}", x.score_at(now, &config.tunables.reputation)))
+            .unwrap_or_default();
+
+        println!(
+            "{:>4}. {:<24} {:>14} {:>14} {:>14} {:>9} {:>6}{}",
+            player.rank,
+            player.name,
+            format_duration(Duration::from_secs(player.total_secs)),
+            format_gap(player.gap_above),
+            format_gap(player.gap_below),
+            player
+                .hold_rate
+                .map(|x| format!("{:.1}%", x * 100.0))
+                .unwrap_or_default(),
+            rep,
+            ours
+        );
+    }
+
+    let names = report
+        .players
+        .iter()
+        .map(|x| (x.user_id.as_str(), x.name.as_str()))
+        .collect::<HashMap<_, _>>();
+
+    if !report.overtakes.is_empty() {
+        println!();
+    }
+
+    for overtake in report.overtakes.iter() {
+        println!(
+            "{} passes {} in {}",
+            names[overtake.behind.as_str()],
+            names[overtake.ahead.as_str()],
+            format_duration(overtake.eta)
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use crate::{
+    admin,
+    backend::{keep_warm, Backend, BackendKind, FakeFlag},
+    config::{reload_on_sighup, Config},
+    context::BotContext,
+    prometheus, reputation, social, stats,
+    supervisor::Supervisor,
+    team::Team,
+    utils::https_client,
+    webhook, yoinker, State,
+};
+use anyhow::Context;
+use futures::future::join_all;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, info_span, Instrument};
+
+/// How many stats updates the main loops remember.
+pub const STATS_HISTORY: usize = 12;
+
+/// Run the yoink bot until cancelled.
+pub async fn run(config: Config, cancellation_token: CancellationToken) -> anyhow::Result<()> {
+    let ctx = Arc::new(BotContext::load(&config)?);
+
+    let (state_tx, _) = watch::channel(Default::default());
+
+    run_bot(config, cancellation_token, ctx, state_tx).await
+}
+
+/// Run the yoink bot until cancelled. The latest state is published on `state_tx`. Anything can subscribe to it.
+pub async fn run_bot(
+    config: Config,
+    cancellation_token: CancellationToken,
+    ctx: Arc<BotContext>,
+    state_tx: watch::Sender<State<STATS_HISTORY>>,
+) -> anyhow::Result<()> {
+    let cancellation_guard = cancellation_token.clone().drop_guard();
+
+    if let Some(metrics_addr) = config.metrics_addr {
+        prometheus::install(metrics_addr)?;
+
+        info!(%metrics_addr, "serving metrics");
+    }
+
+    // create app components
+    let client = https_client().await?;
+
+    let accounts = Team::accounts_from_config(&config, &client)
+        .await
+        .context("loading accounts")?;
+
+    info!(
+        "Hello, {}! Ready to yoink their flags?!",
+        accounts
+            .iter()
+            .map(|x| x.user_id.as_str())
+            .collect::<Vec<_>>()
+            .join(" and ")
+    );
+
+    // only used by the fake backend. our accounts take it from each other like they would in the real game
+    let fake_flag = Arc::new(FakeFlag::new(ctx.tunables().cooldown()));
+
+    let mut yoinker_main_loop_fs = Vec::with_capacity(accounts.len());
+
+    for account in accounts.iter() {
+        let backend =
+            Backend::from_config(&config, client.clone(), &ctx, fake_flag.clone(), account)?;
+
+        let state_rx = state_tx.subscribe();
+
+        let cancellation_token = cancellation_token.clone();
+        let ctx = &ctx;
+        let user_id = account.user_id.as_str();
+
+        // each account keeps its own cooldown state, but they all share the same stats
+        yoinker_main_loop_fs.push(
+            async move {
+                yoinker::main_loop(state_rx, cancellation_token, &backend, account, ctx).await
+            }
+            .instrument(info_span!("account", user_id)),
+        );
+    }
+
+    // spawn background workers. crashed workers are restarted
+    let mut supervisor = Supervisor::new(cancellation_token.clone(), ctx.control.clone());
+
+    {
+        let state_tx = state_tx.clone();
+        let client = client.clone();
+        let ctx = ctx.clone();
+        let team = accounts[0].team.clone();
+
+        // a restarted stats loop picks up from the last published state
+        supervisor.spawn("stats", move |cancellation_token| {
+            stats::stats_loop::<STATS_HISTORY>(
+                state_tx.clone(),
+                cancellation_token,
+                client.clone(),
+                ctx.clone(),
+                team.clone(),
+            )
+        });
+    }
+
+    // remember who takes the flag from us
+    {
+        let ctx = ctx.clone();
+
+        supervisor.spawn("reputation", move |cancellation_token| {
+            reputation::reputation_loop(ctx.clone(), cancellation_token)
+        });
+    }
+
+    if !config.webhooks.is_empty() {
+        let webhooks = config.webhooks.clone();
+        let events = ctx.events.clone();
+
+        supervisor.spawn("webhooks", move |cancellation_token| {
+            webhook::webhook_loop(webhooks.clone(), events.clone(), cancellation_token)
+        });
+    }
+
+    if let Some(admin_addr) = config.admin_addr {
+        let ctx = ctx.clone();
+
+        supervisor.spawn("admin", move |cancellation_token| {
+            admin::serve(admin_addr, ctx.clone(), cancellation_token)
+        });
+    }
+
+    {
+        let config = config.clone();
+        let ctx = ctx.clone();
+
+        // retune the bot without restarting it
+        supervisor.spawn("sighup", move |cancellation_token| {
+            reload_on_sighup(config.clone(), ctx.clone(), cancellation_token)
+        });
+    }
+
+    if config.backend == BackendKind::Neynar {
+        let state_rx = state_tx.subscribe();
+        let client = client.clone();
+        let accounts = accounts.clone();
+        let ctx = ctx.clone();
+
+        // profiles for the social tunables
+        supervisor.spawn("profiles", move |cancellation_token| {
+            social::profiles_loop(
+                state_rx.clone(),
+                cancellation_token,
+                client.clone(),
+                accounts.clone(),
+                ctx.clone(),
+            )
+        });
+    }
+
+    if config.backend == BackendKind::Neynar {
+        let client = client.clone();
+        let clock = ctx.clock.clone();
+
+        supervisor.spawn("keep_warm", move |cancellation_token| {
+            keep_warm(client.clone(), clock.clone(), cancellation_token)
+        });
+    }
+
+    // run the main app
+    // TODO: join multiple futures here? don't try join because we want them all to have time to gracefully shut down
+    let exit = join_all(yoinker_main_loop_fs)
+        .await
+        .into_iter()
+        .collect::<anyhow::Result<Vec<_>>>()
+        .context("yoinker main loop");
+
+    // graceful shutdown
+    drop(cancellation_guard);
+
+    // a task that failed for good is why the main loops stopped. report it first
+    supervisor.join().await?;
+
+    exit?;
+
+    info!("exited successfully");
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::{
+    clock::ServerClock,
+    config::Config,
+    scheduler::{cooldown_path, load_cooldown},
+    stats::fetch_flag,
+    team::Team,
+    utils::{format_duration, https_client},
+};
+use anyhow::Context;
+
+/// Print the current flag holder and when each of our accounts can yoink again.
+pub async fn status(config: Config) -> anyhow::Result<()> {
+    let client = https_client().await?;
+
+    let clock = ServerClock::default();
+
+    let flag = fetch_flag(&client, &clock)
+        .await
+        .context("fetching the flag")?;
+
+    println!(
+        "flag holder: {} ({}) on {}",
+        flag.holder_name, flag.holder_id, flag.holder_platform
+    );
+
+    let accounts = Team::accounts_from_config(&config, &client)
+        .await
+        .context("loading accounts")?;
+
+    for account in accounts.iter() {
+        let cooldown_ends_at = load_cooldown(&cooldown_path(&config.data_dir, &account.user_id))?;
+
+        let cooldown = match cooldown_ends_at.map(|x| (x - clock.server_now()).to_std()) {
+            Some(Ok(remaining)) => format!("cooling down for {}", format_duration(remaining)),
+            Some(Err(_)) | None => "ready".to_string(),
+        };
+
+        if flag.holder_id == account.user_id {
+            println!("{}: {}. holding the flag", account.user_id, cooldown);
+        } else {
+            println!("{}: {}", account.user_id, cooldown);
+        }
+    }
+
+    Ok(())
+}
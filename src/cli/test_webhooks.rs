@@ -0,0 +1,44 @@
+use crate::{
+    config::Config,
+    events::Event,
+    webhook::{send, webhook_client},
+};
+use tokio_util::sync::CancellationToken;
+
+/// Send a sample event to every webhook. Point one at a local receiver to see the payloads.
+pub async fn test_webhooks(
+    config: Config,
+    cancellation_token: CancellationToken,
+) -> anyhow::Result<()> {
+    if config.webhooks.is_empty() {
+        anyhow::bail!("no webhooks are configured");
+    }
+
+    let client = webhook_client()?;
+
+    let event = Event::Yoinked {
+        account: config
+            .user_id
+            .first()
+            .cloned()
+            .unwrap_or_else(|| "farcaster:0".to_string()),
+    };
+
+    let mut healthy = true;
+
+    for (i, webhook) in config.webhooks.iter().enumerate() {
+        match send(&client, webhook, &event, &cancellation_token).await {
+            Ok(()) => println!("ok   webhook #{} ({:?})", i, webhook.format),
+            Err(err) => {
+                println!("FAIL webhook #{} ({:?}): {:#}", i, webhook.format, err);
+                healthy = false;
+            }
+        }
+    }
+
+    if !healthy {
+        anyhow::bail!("some webhooks failed");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,401 @@
+use super::run::{run_bot, STATS_HISTORY};
+use crate::{config::Config, context::BotContext, events::Event, utils::format_duration, State};
+use chrono::{DateTime, Local, Utc};
+use ratatui::{
+    crossterm::event::{self as term, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, List, Paragraph, Row, Table},
+    DefaultTerminal, Frame,
+};
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+use tokio::{
+    sync::{broadcast::error::RecvError, watch},
+    time::{interval, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+/// How often the dashboard redraws. Countdowns tick at this rate.
+const TUI_TICK: Duration = Duration::from_millis(250);
+
+/// How many events the event log keeps.
+const TUI_EVENT_LOG: usize = 200;
+
+/// Run the bot with a live dashboard. Quitting the dashboard shuts the bot down.
+pub async fn tui(config: Config, cancellation_token: CancellationToken) -> anyhow::Result<()> {
+    let ctx = Arc::new(BotContext::load(&config)?);
+
+    let (state_tx, state_rx) = watch::channel(Default::default());
+
+    // subscribe before the bot starts so that we don't miss anything
+    let events = ctx.events.subscribe();
+
+    let bot = run_bot(config, cancellation_token.clone(), ctx.clone(), state_tx);
+
+    let dashboard = async {
+        let mut terminal = ratatui::init();
+
+        let result = Dashboard::new(ctx.clone())
+            .run(&mut terminal, state_rx, events, &cancellation_token)
+            .await;
+
+        ratatui::restore();
+
+        // quitting the dashboard stops the bot
+        cancellation_token.cancel();
+
+        result
+    };
+
+    // the bot isn't Send, so run both on this task
+    let (bot, dashboard) = tokio::join!(bot, dashboard);
+
+    bot.and(dashboard)
+}
+
+struct Dashboard {
+    ctx: Arc<BotContext>,
+    state: State<STATS_HISTORY>,
+    /// The flag holder and when we first saw them with it.
+    holder: Option<(String, Instant)>,
+    event_log: VecDeque<(DateTime<Utc>, String)>,
+}
+
+impl Dashboard {
+    fn new(ctx: Arc<BotContext>) -> Self {
+        Self {
+            ctx,
+            state: Default::default(),
+            holder: None,
+            event_log: VecDeque::with_capacity(TUI_EVENT_LOG),
+        }
+    }
+
+    async fn run(
+        mut self,
+        terminal: &mut DefaultTerminal,
+        mut state_rx: watch::Receiver<State<STATS_HISTORY>>,
+        mut events: tokio::sync::broadcast::Receiver<Event>,
+        cancellation_token: &CancellationToken,
+    ) -> anyhow::Result<()> {
+        let mut tick = interval(TUI_TICK);
+
+        // a closed receiver returns right away. stop polling it so the loop doesn't spin
+        let mut events_open = true;
+
+        loop {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => break,
+                x = state_rx.changed() => match x {
+                    Ok(()) => self.push_state(state_rx.borrow_and_update().clone()),
+                    // the bot stopped
+                    Err(_) => break,
+                },
+                x = events.recv(), if events_open => match x {
+                    Ok(event) => self.push_log(event.message()),
+                    Err(RecvError::Lagged(skipped)) => self.push_log(format!("skipped {} events", skipped)),
+                    Err(RecvError::Closed) => events_open = false,
+                },
+                _ = tick.tick() => {
+                    if !self.handle_keys()? {
+                        break;
+                    }
+
+                    terminal.draw(|frame| self.draw(frame))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_state(&mut self, state: State<STATS_HISTORY>) {
+        if let Some(stats) = state.stats.back() {
+            let holder_id = &stats.flag.holder_id;
+
+            if self.holder.as_ref().map(|x| &x.0) != Some(holder_id) {
+                self.holder = Some((holder_id.clone(), Instant::now()));
+            }
+        }
+
+        self.state = state;
+    }
+
+    fn push_log(&mut self, message: String) {
+        if self.event_log.len() == TUI_EVENT_LOG {
+            self.event_log.pop_front();
+        }
+
+        self.event_log.push_back((Utc::now(), message));
+    }
+
+    /// Returns false when it is time to quit.
+    fn handle_keys(&mut self) -> anyhow::Result<bool> {
+        while term::poll(Duration::ZERO)? {
+            let term::Event::Key(key) = term::read()? else {
+                continue;
+            };
+
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                // raw mode swallows SIGINT
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(false)
+                }
+                KeyCode::Char('p') => {
+                    let paused = !self.ctx.control.is_paused();
+
+                    self.ctx.control.set_paused(paused);
+
+                    self.push_log(if paused { "paused" } else { "resumed" }.to_string());
+                }
+                KeyCode::Char('y') => {
+                    self.ctx.control.force_yoink();
+
+                    self.push_log("forcing a yoink".to_string());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, accounts, bottom, footer] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Length(self.ctx.control.accounts().len() as u16 + 3),
+            Constraint::Min(8),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let [leaderboard, event_log] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(bottom);
+
+        self.draw_header(frame, header);
+        self.draw_accounts(frame, accounts);
+        self.draw_leaderboard(frame, leaderboard);
+        self.draw_event_log(frame, event_log);
+
+        frame.render_widget(
+            Paragraph::new("q quit  p pause/resume  y force a yoink").dim(),
+            footer,
+        );
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let holder = match (self.state.stats.back(), &self.holder) {
+            (Some(stats), Some((_, since))) => {
+                let name = stats
+                    .users
+                    .get(&stats.flag.holder_id)
+                    .unwrap_or(&stats.flag.holder_name);
+
+                format!(
+                    "{} ({}) for at least {}",
+                    name,
+                    stats.flag.holder_id,
+                    format_duration(since.elapsed())
+                )
+            }
+            _ => "waiting for stats".to_string(),
+        };
+
+        let mode = if self.ctx.control.is_paused() {
+            "PAUSED".red().bold()
+        } else {
+            "running".green()
+        };
+
+        let unhealthy = self
+            .ctx
+            .control
+            .tasks()
+            .into_iter()
+            .filter(|(_, health)| !health.is_healthy())
+            .map(|(task, _)| task)
+            .collect::<Vec<_>>();
+
+        let tasks = if unhealthy.is_empty() {
+            "ok".green()
+        } else {
+            format!("failing: {}", unhealthy.join(", ")).red().bold()
+        };
+
+        let lines = vec![
+            Line::from(vec!["holder: ".into(), holder.bold()]),
+            Line::from(vec![
+                "strategy: ".into(),
+                self.ctx.tunables().strategy.as_str().into(),
+                "  intensity: ".into(),
+                self.ctx.tunables().schedule.intensity_now().as_str().into(),
+                "  ".into(),
+                mode,
+            ]),
+            Line::from(vec!["tasks: ".into(), tasks]),
+        ];
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("yoinker")),
+            area,
+        );
+    }
+
+    fn draw_accounts(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let leaderboard = self
+            .state
+            .stats
+            .back()
+            .map(|x| x.leaderboard())
+            .unwrap_or_default();
+
+        let now = Utc::now();
+
+        let rows = self
+            .ctx
+            .control
+            .accounts()
+            .into_iter()
+            .map(|(user_id, status)| {
+                let rank = leaderboard
+                    .iter()
+                    .position(|(x, _)| **x == user_id)
+                    .map(|x| format!("#{}", x + 1))
+                    .unwrap_or_else(|| "-".to_string());
+
+                let time = leaderboard
+                    .iter()
+                    .find(|(x, _)| **x == user_id)
+                    .map(|(_, secs)| format_duration(Duration::from_secs(*secs)))
+                    .unwrap_or_default();
+
+                // cooldowns are in server time. close enough for a countdown
+                let cooldown = match status.cooldown_ends_at.map(|x| (x - now).to_std()) {
+                    Some(Ok(remaining)) => format_duration(remaining),
+                    _ => "ready".to_string(),
+                };
+
+                let last_decision = status
+                    .last_decision
+                    .map(|x| {
+                        format!(
+                            "{}: {} at {}",
+                            x.strategy,
+                            x.decision,
+                            x.at.with_timezone(&Local).format("%H:%M:%S")
+                        )
+                    })
+                    .unwrap_or_default();
+
+                Row::new([
+                    user_id,
+                    rank,
+                    time,
+                    cooldown,
+                    last_decision,
+                    status.consecutive_failures.to_string(),
+                ])
+            });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(20),
+                Constraint::Length(6),
+                Constraint::Length(14),
+                Constraint::Length(12),
+                Constraint::Min(24),
+                Constraint::Length(8),
+            ],
+        )
+        .header(
+            Row::new([
+                "account",
+                "rank",
+                "time held",
+                "cooldown",
+                "last decision",
+                "failures",
+            ])
+            .bold(),
+        )
+        .block(Block::bordered().title("our accounts"));
+
+        frame.render_widget(table, area);
+    }
+
+    fn draw_leaderboard(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let Some(stats) = self.state.stats.back() else {
+            frame.render_widget(Block::bordered().title("leaderboard"), area);
+            return;
+        };
+
+        let ours = self.ctx.control.accounts();
+
+        let rows = stats
+            .leaderboard()
+            .into_iter()
+            .enumerate()
+            .map(|(rank, (user_id, secs))| {
+                let name = stats.users.get(user_id).unwrap_or(user_id);
+
+                // how much time they gained over the stats that we remember
+                let gained = self
+                    .state
+                    .diff
+                    .get(user_id)
+                    .map(|x| format!("+{}", format_duration(Duration::from_secs(*x))))
+                    .unwrap_or_default();
+
+                let row = Row::new([
+                    format!("{}", rank + 1),
+                    name.clone(),
+                    format_duration(Duration::from_secs(secs)),
+                    gained,
+                ]);
+
+                if ours.contains_key(user_id) {
+                    row.style(Style::new().bold().yellow())
+                } else {
+                    row
+                }
+            });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(4),
+                Constraint::Min(16),
+                Constraint::Length(14),
+                Constraint::Length(12),
+            ],
+        )
+        .header(Row::new(["#", "name", "time held", "gained"]).bold())
+        .block(Block::bordered().title("leaderboard"));
+
+        frame.render_widget(table, area);
+    }
+
+    fn draw_event_log(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        // newest first so that the latest events are always on screen
+        let items = self.event_log.iter().rev().map(|(at, message)| {
+            format!(
+                "{} {}",
+                at.with_timezone(&Local).format("%H:%M:%S"),
+                message
+            )
+        });
+
+        frame.render_widget(
+            List::new(items).block(Block::bordered().title("events")),
+            area,
+        );
+    }
+}
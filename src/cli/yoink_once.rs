@@ -0,0 +1,44 @@
+use crate::{
+    backend::{Backend, FakeFlag, YoinkBackend},
+    config::Config,
+    context::BotContext,
+    scheduler::FireScheduler,
+    team::Team,
+    utils::https_client,
+};
+use anyhow::Context;
+use std::{sync::Arc, time::Instant};
+
+/// Yoink the flag once with one account and print the outcome. No strategy is consulted.
+pub async fn yoink_once(config: Config, user_id: Option<String>) -> anyhow::Result<()> {
+    let client = https_client().await?;
+
+    let accounts = Team::accounts_from_config(&config, &client)
+        .await
+        .context("loading accounts")?;
+
+    let account = match user_id {
+        Some(user_id) => accounts
+            .iter()
+            .find(|x| x.user_id == user_id)
+            .with_context(|| format!("{} is not one of our accounts", user_id))?,
+        None => &accounts[0],
+    };
+
+    let ctx = Arc::new(BotContext::in_memory(&config));
+
+    let fake_flag = Arc::new(FakeFlag::new(ctx.tunables().cooldown()));
+
+    let backend = Backend::from_config(&config, client, &ctx, fake_flag, account)?;
+
+    // save the cooldown so that `run` and `status` know about it
+    let mut scheduler = FireScheduler::new(ctx.clock.clone(), &ctx.data_dir, &account.user_id);
+
+    let outcome = backend.yoink(Instant::now()).await?;
+
+    scheduler.record(&outcome, ctx.tunables().cooldown());
+
+    println!("{}: {:?}", account.user_id, outcome);
+
+    Ok(())
+}
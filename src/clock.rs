@@ -0,0 +1,305 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use reqwest::header::{HeaderMap, DATE};
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{trace, warn};
+
+/// How much weight a new latency sample gets.
+const LATENCY_ALPHA: f64 = 0.2;
+
+/// How many clock samples to keep.
+const MAX_SAMPLES: usize = 32;
+
+/// Clocks drift. Forget samples older than this.
+const MAX_SAMPLE_AGE: Duration = Duration::from_secs(30 * 60);
+
+/// `Date` headers only have whole seconds.
+const DATE_HEADER_RESOLUTION: TimeDelta = TimeDelta::seconds(1);
+
+/// Our best guess of server time minus local time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockEstimate {
+    /// server time minus local time
+    pub offset: TimeDelta,
+    /// the real offset is within `offset ± uncertainty`
+    pub uncertainty: TimeDelta,
+}
+
+impl ClockEstimate {
+    /// The lowest offset that agrees with our samples.
+    pub fn min(&self) -> TimeDelta {
+        self.offset - self.uncertainty
+    }
+
+    /// The highest offset that agrees with our samples.
+    pub fn max(&self) -> TimeDelta {
+        self.offset + self.uncertainty
+    }
+}
+
+/// One observation of the servers' clocks. The real offset is somewhere in `lo..=hi`.
+#[derive(Clone, Copy, Debug)]
+struct ClockSample {
+    lo: TimeDelta,
+    /// `None` if this sample only gave us a lower bound
+    hi: Option<TimeDelta>,
+    observed_at: Instant,
+}
+
+/// Our view of the servers' clocks and of how long our requests take to reach them.
+///
+/// Every response bounds the offset between our clock and the server's. Intersecting those bounds gives an estimate that is much tighter than any single `Date` header.
+/// Shared by everything that talks to the game or to Neynar.
+#[derive(Debug, Default)]
+pub struct ServerClock {
+    inner: Mutex<ServerClockInner>,
+}
+
+#[derive(Debug, Default)]
+struct ServerClockInner {
+    samples: VecDeque<ClockSample>,
+    /// moving average of the one-way request latency
+    latency: Option<Duration>,
+}
+
+impl ServerClockInner {
+    fn push_sample(&mut self, lo: TimeDelta, hi: Option<TimeDelta>) {
+        let now = Instant::now();
+
+        self.samples
+            .retain(|x| now.duration_since(x.observed_at) < MAX_SAMPLE_AGE);
+
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(ClockSample {
+            lo,
+            hi,
+            observed_at: now,
+        });
+    }
+
+    fn estimate(&self) -> Option<ClockEstimate> {
+        let newest = self.samples.back()?;
+
+        let lo = self.samples.iter().map(|x| x.lo).max()?;
+
+        // only samples with an upper bound can narrow the estimate from above
+        let hi = self.samples.iter().filter_map(|x| x.hi).min();
+
+        let (lo, hi) = match (hi, newest.hi) {
+            (Some(hi), _) if lo <= hi => (lo, hi),
+            (Some(_), Some(newest_hi)) => {
+                // the samples disagree. a clock probably jumped. trust the newest
+                (newest.lo, newest_hi)
+            }
+            _ => {
+                // we only have lower bounds
+                (lo, lo)
+            }
+        };
+
+        let uncertainty = (hi - lo) / 2;
+
+        Some(ClockEstimate {
+            offset: lo + uncertainty,
+            uncertainty,
+        })
+    }
+}
+
+impl ServerClock {
+    /// Learn from a response that was requested at `sent` and arrived at `received` (both local time).
+    pub fn observe_response(
+        &self,
+        headers: &HeaderMap,
+        sent: DateTime<Utc>,
+        received: DateTime<Utc>,
+    ) {
+        let one_way = (received - sent).to_std().unwrap_or_default() / 2;
+
+        let server_date = headers
+            .get(DATE)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| DateTime::parse_from_rfc2822(x).ok())
+            .map(|x| x.with_timezone(&Utc));
+
+        let mut inner = self.inner.lock().expect("clock lock poisoned");
+
+        inner.latency = Some(match inner.latency {
+            None => one_way,
+            Some(old) => old.mul_f64(1.0 - LATENCY_ALPHA) + one_way.mul_f64(LATENCY_ALPHA),
+        });
+
+        if let Some(server_date) = server_date {
+            // the server stamped the response between `sent` and `received` and then truncated it to the second
+            let lo = server_date - received;
+            let hi = server_date + DATE_HEADER_RESOLUTION - sent;
+
+            inner.push_sample(lo, Some(hi));
+
+            trace!(estimate = ?inner.estimate(), ?one_way, "observed server date");
+        } else {
+            warn!("response had no date header");
+        }
+    }
+
+    /// Learn from a millisecond timestamp that the server generated at or before `received`.
+    ///
+    /// This only tells us that the server's clock was at least this far along.
+    pub fn observe_server_timestamp(&self, server: DateTime<Utc>, received: DateTime<Utc>) {
+        let mut inner = self.inner.lock().expect("clock lock poisoned");
+
+        inner.push_sample(server - received, None);
+
+        trace!(estimate = ?inner.estimate(), "observed server timestamp");
+    }
+
+    /// Our best guess of the offset. `None` until we've seen a response.
+    pub fn estimate(&self) -> Option<ClockEstimate> {
+        self.inner.lock().expect("clock lock poisoned").estimate()
+    }
+
+    /// The current time on the servers' clock.
+    pub fn server_now(&self) -> DateTime<Utc> {
+        self.to_server(Utc::now())
+    }
+
+    /// Convert local time to server time.
+    pub fn to_server(&self, local: DateTime<Utc>) -> DateTime<Utc> {
+        local + self.offset()
+    }
+
+    /// Convert server time to local time.
+    pub fn to_local(&self, server: DateTime<Utc>) -> DateTime<Utc> {
+        server - self.offset()
+    }
+
+    /// Is this server time definitely in the past? False if our uncertainty covers it.
+    pub fn is_past(&self, server: DateTime<Utc>) -> bool {
+        let now = Utc::now();
+
+        match self.estimate() {
+            Some(x) => server < now + x.min(),
+            None => server < now,
+        }
+    }
+
+    /// Server time minus local time. Zero until we've seen a response.
+    pub fn offset(&self) -> TimeDelta {
+        self.estimate().map(|x| x.offset).unwrap_or_default()
+    }
+
+    /// How long a request takes to reach the server. Zero until we've seen a response.
+    pub fn latency(&self) -> Duration {
+        self.inner
+            .lock()
+            .expect("clock lock poisoned")
+            .latency
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(x: i64) -> TimeDelta {
+        TimeDelta::milliseconds(x)
+    }
+
+    #[test]
+    fn no_estimate_without_samples() {
+        assert_eq!(ServerClockInner::default().estimate(), None);
+    }
+
+    #[test]
+    fn samples_are_intersected() {
+        let mut inner = ServerClockInner::default();
+
+        inner.push_sample(ms(0), Some(ms(1_000)));
+        inner.push_sample(ms(400), Some(ms(1_400)));
+        inner.push_sample(ms(-200), Some(ms(600)));
+
+        assert_eq!(
+            inner.estimate(),
+            Some(ClockEstimate {
+                offset: ms(500),
+                uncertainty: ms(100),
+            })
+        );
+    }
+
+    #[test]
+    fn lower_bounds_alone_give_the_highest() {
+        let mut inner = ServerClockInner::default();
+
+        inner.push_sample(ms(300), None);
+        inner.push_sample(ms(100), None);
+
+        assert_eq!(
+            inner.estimate(),
+            Some(ClockEstimate {
+                offset: ms(300),
+                uncertainty: ms(0),
+            })
+        );
+    }
+
+    #[test]
+    fn disagreeing_samples_trust_the_newest() {
+        let mut inner = ServerClockInner::default();
+
+        inner.push_sample(ms(0), Some(ms(1_000)));
+        // the server's clock jumped forward
+        inner.push_sample(ms(5_000), Some(ms(6_000)));
+
+        assert_eq!(
+            inner.estimate(),
+            Some(ClockEstimate {
+                offset: ms(5_500),
+                uncertainty: ms(500),
+            })
+        );
+    }
+
+    #[test]
+    fn only_the_newest_samples_are_kept() {
+        let mut inner = ServerClockInner::default();
+
+        inner.push_sample(ms(10_000), None);
+
+        for _ in 0..MAX_SAMPLES {
+            inner.push_sample(ms(0), Some(ms(1_000)));
+        }
+
+        assert_eq!(inner.samples.len(), MAX_SAMPLES);
+        assert_eq!(inner.estimate().map(|x| x.offset), Some(ms(500)));
+    }
+
+    #[test]
+    fn date_headers_bound_the_offset() {
+        let clock = ServerClock::default();
+
+        let sent = DateTime::parse_from_rfc3339("2024-01-01T00:00:00.200Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let received = sent + ms(400);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(DATE, "Mon, 01 Jan 2024 00:00:10 GMT".parse().unwrap());
+
+        clock.observe_response(&headers, sent, received);
+
+        let estimate = clock.estimate().unwrap();
+
+        // the server stamped somewhere in 10.0..11.0 while we were between 0.2 and 0.6
+        assert_eq!(estimate.min(), ms(9_400));
+        assert_eq!(estimate.max(), ms(10_800));
+        assert_eq!(clock.latency(), Duration::from_millis(200));
+    }
+}
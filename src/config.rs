@@ -0,0 +1,776 @@
+use crate::{
+    backend::BackendKind,
+    context::BotContext,
+    schedule::Schedule,
+    secret::{read_secrets_file, Secret},
+    sleep::TimingProfile,
+    strategy::StrategyKind,
+    webhook::WebhookConfig,
+};
+use anyhow::Context;
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+use std::{
+    collections::BTreeMap,
+    env,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// Used when CONFIG_FILE isn't set. It is fine if this file doesn't exist.
+pub const DEFAULT_CONFIG_FILE: &str = "yoinker.toml";
+
+/// How long the game makes a player wait between yoinks. Used when `tunables.cooldown_secs` isn't set.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(10 * 60);
+
+/// Scaled to `tunables.cooldown_secs` unless `tunables.timing_profiles` defines it.
+pub const DEFAULT_TIMING_PROFILE: &str = "default";
+
+/// Top level keys that can be overridden by environment variables. `TUNABLES__COOLDOWN_SECS` overrides `tunables.cooldown_secs`.
+const ENV_KEYS: [&str; 11] = [
+    "user_id",
+    "cast_hash",
+    "nn_api_key",
+    "nn_api_key_file",
+    "nn_signer_uuid",
+    "nn_signer_uuid_file",
+    "backend",
+    "data_dir",
+    "metrics_addr",
+    "admin_addr",
+    "tunables",
+];
+
+/// Keys that hold lists. Their environment variables are comma separated.
+const ENV_LIST_KEYS: [&str; 3] = ["user_id", "nn_api_key", "nn_signer_uuid"];
+
+/// application configuration. secrets are redacted from Debug.
+///
+/// Loaded from a TOML file with environment variable overrides. Only [Tunables] can be reloaded without a restart.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// one per account. comma separated in the environment.
+    /// optional. the user ids are looked up from the signers. if set, they must match
+    #[serde(default)]
+    pub user_id: Vec<String>,
+    pub cast_hash: String,
+    /// either one shared by all accounts or one per account. only required by the neynar backend
+    #[serde(default)]
+    pub nn_api_key: Vec<Secret<String>>,
+    /// read `nn_api_key` from this file instead
+    pub nn_api_key_file: Option<PathBuf>,
+    /// one per account. only required by the neynar backend
+    #[serde(default)]
+    pub nn_signer_uuid: Vec<Secret<String>>,
+    /// read `nn_signer_uuid` from this file instead
+    pub nn_signer_uuid_file: Option<PathBuf>,
+    /// how frame actions are submitted. defaults to neynar
+    #[serde(default)]
+    pub backend: BackendKind,
+    /// where state that should survive a restart is kept. defaults to ./data
+    #[serde(default = "default_data_dir")]
+    pub data_dir: PathBuf,
+    /// serve prometheus metrics here. off if unset
+    pub metrics_addr: Option<SocketAddr>,
+    /// serve the admin api here. off if unset. it has no auth so keep it on localhost
+    pub admin_addr: Option<SocketAddr>,
+    /// where to send events. only settable in the config file
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    #[serde(default)]
+    pub tunables: Tunables,
+}
+
+fn default_data_dir() -> PathBuf {
+    PathBuf::from("data")
+}
+
+/// Settings that can change while the bot is running. Reloaded on SIGHUP.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tunables {
+    pub strategy: StrategyKind,
+    /// TODO: this might change! we should get this from stats or something like that
+    pub cooldown_secs: u64,
+    /// stats update every 30 minutes, but we don't know where in the refresh window we are. so only cache for 5 minutes
+    pub stats_cache_ttl_secs: u64,
+    /// how long the main loop waits for new state before running anyways
+    pub state_timeout_ms: u64,
+    /// which of `timing_profiles` every random delay comes from
+    pub timing_profile: String,
+    pub timing_profiles: BTreeMap<String, TimingProfile>,
+    /// when to play and how hard
+    pub schedule: Schedule,
+    pub staleness: StalenessTunables,
+    pub reputation: ReputationTunables,
+    pub mostly_nice: MostlyNiceTunables,
+    pub tit_for_tat: TitForTatTunables,
+    pub hold_value: HoldValueTunables,
+    pub social: SocialTunables,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StalenessTunables {
+    /// state that we haven't heard about in this long is stale. stats change about every 30 minutes
+    pub max_age_secs: u64,
+    pub policy: StalePolicy,
+}
+
+/// What the main loop does with stale state.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StalePolicy {
+    /// play like it is fresh
+    Keep,
+    /// play passively. only snipe targets
+    #[default]
+    Conservative,
+    /// don't yoink until fresh state arrives
+    Pause,
+}
+
+/// The survival model behind the hold value strategy.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HoldValueTunables {
+    /// players who yoinked within this long are counted as active
+    pub active_window_secs: u64,
+    /// don't estimate anything until we've watched this many holds
+    pub min_holds: usize,
+    /// how many holds' worth of the overall rate each hour of the day starts with
+    pub prior_holds: f64,
+    /// how far apart the times that we consider waiting for are
+    pub step_secs: u64,
+}
+
+/// Change decisions based on the holder's Farcaster profile. Profiles are only looked up with the neynar backend.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SocialTunables {
+    /// look profiles up again after this long
+    pub ttl_secs: u64,
+    /// never yoink from players that follow us and that we follow
+    pub spare_mutuals: bool,
+    /// snipe likely bots even when the strategy wants to wait. players that we follow are never bots
+    pub snipe_bots: bool,
+    /// players with this many followers or fewer are likely bots
+    pub bot_max_followers: u64,
+}
+
+/// How grudges against players who take the flag from us grow and fade.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReputationTunables {
+    /// a score loses half of its value this often
+    pub half_life_secs: u64,
+    /// taking the flag this soon after we got it counts extra
+    pub quick_secs: u64,
+    /// how much a quick yoink counts. a slow one counts 1
+    pub quick_weight: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MostlyNiceTunables {
+    /// holders below this many seconds are left alone (most of the time)
+    pub jerk_threshold_secs: u64,
+    /// percent chance of leaving a low scoring holder alone
+    pub nice_chance: u16,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TitForTatTunables {
+    /// retaliate against holders with at least this reputation score
+    pub retaliate_score: f64,
+}
+
+impl Default for Tunables {
+    fn default() -> Self {
+        Self {
+            strategy: Default::default(),
+            cooldown_secs: DEFAULT_COOLDOWN.as_secs(),
+            stats_cache_ttl_secs: 5 * 60,
+            state_timeout_ms: 3_000,
+            timing_profile: DEFAULT_TIMING_PROFILE.to_string(),
+            timing_profiles: Default::default(),
+            schedule: Default::default(),
+            staleness: Default::default(),
+            reputation: Default::default(),
+            mostly_nice: Default::default(),
+            tit_for_tat: Default::default(),
+            hold_value: Default::default(),
+            social: Default::default(),
+        }
+    }
+}
+
+impl Default for StalenessTunables {
+    fn default() -> Self {
+        Self {
+            max_age_secs: 45 * 60,
+            policy: Default::default(),
+        }
+    }
+}
+
+impl Default for ReputationTunables {
+    fn default() -> Self {
+        Self {
+            half_life_secs: 6 * 3600,
+            quick_secs: 2 * 60,
+            quick_weight: 3.0,
+        }
+    }
+}
+
+impl Default for MostlyNiceTunables {
+    fn default() -> Self {
+        Self {
+            jerk_threshold_secs: 6 * 3600,
+            nice_chance: 75,
+        }
+    }
+}
+
+impl Default for TitForTatTunables {
+    fn default() -> Self {
+        // one yoink is held against them for one half life. a quick one for about two and a half
+        Self {
+            retaliate_score: 0.5,
+        }
+    }
+}
+
+impl Default for HoldValueTunables {
+    fn default() -> Self {
+        Self {
+            active_window_secs: 3600,
+            min_holds: 20,
+            prior_holds: 5.0,
+            step_secs: 60,
+        }
+    }
+}
+
+impl Default for SocialTunables {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 6 * 3600,
+            spare_mutuals: false,
+            snipe_bots: false,
+            bot_max_followers: 0,
+        }
+    }
+}
+
+impl Tunables {
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.cooldown_secs)
+    }
+
+    pub fn stats_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.stats_cache_ttl_secs)
+    }
+
+    pub fn state_timeout(&self) -> Duration {
+        Duration::from_millis(self.state_timeout_ms)
+    }
+
+    /// The selected timing profile.
+    pub fn timing(&self) -> TimingProfile {
+        match self.timing_profiles.get(&self.timing_profile) {
+            Some(x) => x.clone(),
+            None if self.timing_profile == DEFAULT_TIMING_PROFILE => {
+                TimingProfile::for_cooldown(self.cooldown())
+            }
+            None => unreachable!("validate checks that the timing profile exists"),
+        }
+    }
+
+    /// Check that durations are non-zero, that the chosen timing profile exists, and that every ratio and score is in range.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.cooldown_secs == 0 {
+            anyhow::bail!("tunables.cooldown_secs must be > 0");
+        }
+        if self.stats_cache_ttl_secs == 0 {
+            anyhow::bail!("tunables.stats_cache_ttl_secs must be > 0");
+        }
+        if self.state_timeout_ms == 0 {
+            anyhow::bail!("tunables.state_timeout_ms must be > 0");
+        }
+        if self.timing_profile != DEFAULT_TIMING_PROFILE
+            && !self.timing_profiles.contains_key(&self.timing_profile)
+        {
+            anyhow::bail!(
+                "tunables.timing_profile ({}) must be {} or one of tunables.timing_profiles: {:?}",
+                self.timing_profile,
+                DEFAULT_TIMING_PROFILE,
+                self.timing_profiles.keys().collect::<Vec<_>>()
+            );
+        }
+        for (name, profile) in self.timing_profiles.iter() {
+            profile.validate(name)?;
+        }
+        self.schedule.validate()?;
+        if self.mostly_nice.nice_chance > 100 {
+            anyhow::bail!(
+                "tunables.mostly_nice.nice_chance ({}) is a percentage. it must be <= 100",
+                self.mostly_nice.nice_chance
+            );
+        }
+        if self.reputation.half_life_secs == 0 {
+            anyhow::bail!("tunables.reputation.half_life_secs must be > 0");
+        }
+        if !self.reputation.quick_weight.is_finite() || self.reputation.quick_weight < 0.0 {
+            anyhow::bail!(
+                "tunables.reputation.quick_weight ({}) must be >= 0",
+                self.reputation.quick_weight
+            );
+        }
+        if !self.tit_for_tat.retaliate_score.is_finite() || self.tit_for_tat.retaliate_score <= 0.0
+        {
+            anyhow::bail!(
+                "tunables.tit_for_tat.retaliate_score ({}) must be > 0. everyone would be a target",
+                self.tit_for_tat.retaliate_score
+            );
+        }
+        if self.hold_value.active_window_secs == 0 {
+            anyhow::bail!("tunables.hold_value.active_window_secs must be > 0");
+        }
+        if !self.hold_value.prior_holds.is_finite() || self.hold_value.prior_holds <= 0.0 {
+            anyhow::bail!(
+                "tunables.hold_value.prior_holds ({}) must be > 0",
+                self.hold_value.prior_holds
+            );
+        }
+        if self.hold_value.step_secs == 0 {
+            anyhow::bail!("tunables.hold_value.step_secs must be > 0");
+        }
+        if self.social.ttl_secs == 0 {
+            anyhow::bail!("tunables.social.ttl_secs must be > 0");
+        }
+
+        Ok(())
+    }
+}
+
+impl StalenessTunables {
+    pub fn max_age(&self) -> Duration {
+        Duration::from_secs(self.max_age_secs)
+    }
+}
+
+impl Config {
+    /// Load the config from the TOML file at CONFIG_FILE (or [DEFAULT_CONFIG_FILE]) and then apply environment variable overrides.
+    ///
+    /// Secrets can come from `*_FILE` settings instead.
+    pub fn load() -> anyhow::Result<Self> {
+        let (path, required) = match env::var_os("CONFIG_FILE") {
+            Some(x) => (PathBuf::from(x), true),
+            None => (PathBuf::from(DEFAULT_CONFIG_FILE), false),
+        };
+
+        let mut table = if required || path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("reading {}", path.display()))?;
+
+            toml::from_str::<toml::Table>(&contents)
+                .with_context(|| format!("parsing {}", path.display()))?
+        } else {
+            toml::Table::new()
+        };
+
+        apply_env_overrides(&mut table, env::vars());
+
+        let mut config = Self::deserialize(ConfigValue(toml::Value::Table(table)))
+            .with_context(|| format!("invalid config in {} or the environment", path.display()))?;
+
+        // the other backends never talk to neynar
+        let required = config.backend == BackendKind::Neynar;
+
+        load_secrets(
+            "nn_api_key",
+            &mut config.nn_api_key,
+            config.nn_api_key_file.as_deref(),
+            required,
+        )?;
+        load_secrets(
+            "nn_signer_uuid",
+            &mut config.nn_signer_uuid,
+            config.nn_signer_uuid_file.as_deref(),
+            required,
+        )?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Check that the cast hash looks like one, then validate the tunables.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.cast_hash.starts_with("0x") {
+            anyhow::bail!("cast_hash should start with 0x. got {}", self.cast_hash);
+        }
+
+        self.tunables.validate()
+    }
+}
+
+/// Environment variables win over the config file. Nested keys are separated by `__`.
+///
+/// Values stay strings. [ConfigValue] parses them once a field asks for something else.
+fn apply_env_overrides(table: &mut toml::Table, vars: impl Iterator<Item = (String, String)>) {
+    for (key, value) in vars {
+        let key = key.to_lowercase();
+
+        let path = key.split("__").collect::<Vec<_>>();
+
+        if !ENV_KEYS.contains(&path[0]) {
+            continue;
+        }
+
+        if value.is_empty() {
+            // `KEY=` in a .env file means unset
+            continue;
+        }
+
+        let value = if ENV_LIST_KEYS.contains(&key.as_str()) {
+            toml::Value::Array(
+                value
+                    .split(',')
+                    .map(|x| toml::Value::String(x.trim().to_string()))
+                    .collect(),
+            )
+        } else {
+            toml::Value::String(value)
+        };
+
+        let (last, parents) = path.split_last().expect("split always has one item");
+
+        let mut table = &mut *table;
+        for parent in parents {
+            let entry = table
+                .entry(parent.to_string())
+                .or_insert_with(|| toml::Value::Table(Default::default()));
+
+            if !entry.is_table() {
+                *entry = toml::Value::Table(Default::default());
+            }
+
+            table = entry.as_table_mut().expect("entry was just made a table");
+        }
+
+        table.insert(last.to_string(), value);
+    }
+}
+
+/// The merged config file and environment. Strings are parsed when a field wants a number or a bool.
+///
+/// `CAST_HASH=123` stays a string while `TUNABLES__COOLDOWN_SECS=60` becomes a number.
+struct ConfigValue(toml::Value);
+
+/// Parse strings into whatever the field's visitor takes. Other values deserialize like usual.
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match self.0 {
+                    toml::Value::String(x) => visitor.$visit(
+                        x.parse()
+                            .map_err(|err| de::Error::custom(format!("{:?}: {}", x, err)))?,
+                    ),
+                    x => x.$method(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ConfigValue {
+    type Error = toml::de::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            toml::Value::Table(x) => {
+                let mut map = MapDeserializer::new(x.into_iter().map(|(k, v)| (k, ConfigValue(v))));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            toml::Value::Array(x) => {
+                let mut seq = SeqDeserializer::new(x.into_iter().map(ConfigValue));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            x => x.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        // a missing key is the only way to get `None`
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, toml::de::Error> for ConfigValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Fill `secrets` from `file` if it was given. Only one of the two can be set. If `required`, one of them must be.
+fn load_secrets(
+    name: &str,
+    secrets: &mut Vec<Secret<String>>,
+    file: Option<&Path>,
+    required: bool,
+) -> anyhow::Result<()> {
+    match (secrets.is_empty(), file) {
+        (true, Some(file)) => {
+            *secrets = read_secrets_file(file)?;
+        }
+        (false, Some(_)) => {
+            anyhow::bail!("set {} or {}_file. not both", name, name);
+        }
+        (true, None) if required => {
+            anyhow::bail!("{} or {}_file is required", name, name);
+        }
+        (_, None) => {}
+    }
+
+    Ok(())
+}
+
+/// Reload the config every time we get SIGHUP. Only the tunables are applied. Everything else needs a restart.
+pub async fn reload_on_sighup(
+    config: Config,
+    ctx: Arc<BotContext>,
+    cancellation_token: CancellationToken,
+) -> anyhow::Result<()> {
+    let mut sighup = signal(SignalKind::hangup()).context("listening for SIGHUP")?;
+
+    loop {
+        tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            x = sighup.recv() => if x.is_none() { break },
+        }
+
+        info!("[SIGHUP] received. reloading config");
+
+        let new_config = match Config::load() {
+            Ok(x) => x,
+            Err(err) => {
+                error!(?err, "invalid config. keeping the old one");
+                continue;
+            }
+        };
+
+        if new_config.user_id != config.user_id
+            || new_config.cast_hash != config.cast_hash
+            || new_config.nn_api_key != config.nn_api_key
+            || new_config.nn_signer_uuid != config.nn_signer_uuid
+            || new_config.backend != config.backend
+            || new_config.data_dir != config.data_dir
+            || new_config.metrics_addr != config.metrics_addr
+            || new_config.admin_addr != config.admin_addr
+            || new_config.webhooks != config.webhooks
+        {
+            warn!("accounts, secrets, backend, data_dir, listen address, and webhook changes need a restart. only applying tunables");
+        }
+
+        if *ctx.tunables() == new_config.tunables {
+            info!("tunables unchanged");
+        } else {
+            // this also drops a strategy that was set through the admin api
+            info!(tunables = ?new_config.tunables, "applying new tunables");
+            ctx.set_tunables(new_config.tunables);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overridden(file: &str, vars: &[(&str, &str)]) -> toml::Table {
+        let mut table = toml::from_str(file).unwrap();
+
+        apply_env_overrides(
+            &mut table,
+            vars.iter().map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+
+        table
+    }
+
+    #[test]
+    fn env_wins_over_the_file() {
+        let table = overridden(
+            "cast_hash = \"0xfile\"\nbackend = \"neynar\"",
+            &[("CAST_HASH", "0xenv"), ("HOME", "/root")],
+        );
+
+        assert_eq!(table["cast_hash"].as_str(), Some("0xenv"));
+        assert_eq!(table["backend"].as_str(), Some("neynar"));
+
+        // only our keys are read
+        assert!(!table.contains_key("home"));
+    }
+
+    #[test]
+    fn env_lists_are_comma_separated() {
+        let table = overridden("", &[("USER_ID", "farcaster:1, farcaster:2")]);
+
+        let user_ids = table["user_id"].as_array().unwrap();
+
+        assert_eq!(user_ids.len(), 2);
+        assert_eq!(user_ids[1].as_str(), Some("farcaster:2"));
+    }
+
+    #[test]
+    fn env_sets_nested_tunables() {
+        let table = overridden(
+            "[tunables]\nstrategy = \"red_shell\"",
+            &[
+                ("TUNABLES__COOLDOWN_SECS", "60"),
+                ("TUNABLES__SOCIAL__SPARE_MUTUALS", "false"),
+                ("TUNABLES__REPUTATION__QUICK_WEIGHT", "1.5"),
+            ],
+        );
+
+        let tunables = Tunables::deserialize(ConfigValue(table["tunables"].clone())).unwrap();
+
+        assert_eq!(tunables.strategy.as_str(), "red_shell");
+        assert_eq!(tunables.cooldown_secs, 60);
+        assert!(!tunables.social.spare_mutuals);
+        assert_eq!(tunables.reputation.quick_weight, 1.5);
+    }
+
+    #[test]
+    fn env_strings_are_parsed_per_field() {
+        let table = overridden(
+            "",
+            &[("CAST_HASH", "12345"), ("TUNABLES__COOLDOWN_SECS", "60")],
+        );
+
+        let config = Config::deserialize(ConfigValue(toml::Value::Table(table))).unwrap();
+
+        // looks like a number but the field is a string
+        assert_eq!(config.cast_hash, "12345");
+        assert_eq!(config.tunables.cooldown_secs, 60);
+
+        let table = overridden(
+            "",
+            &[("CAST_HASH", "0x1"), ("TUNABLES__COOLDOWN_SECS", "soon")],
+        );
+
+        assert!(Config::deserialize(ConfigValue(toml::Value::Table(table))).is_err());
+    }
+
+    #[test]
+    fn empty_env_vars_are_unset() {
+        let table = overridden("cast_hash = \"0xfile\"", &[("CAST_HASH", "")]);
+
+        assert_eq!(table["cast_hash"].as_str(), Some("0xfile"));
+    }
+
+    #[test]
+    fn secrets_are_only_required_when_asked_for() {
+        let mut secrets = vec![];
+
+        assert!(load_secrets("nn_api_key", &mut secrets, None, true).is_err());
+        assert!(load_secrets("nn_api_key", &mut secrets, None, false).is_ok());
+
+        // both is a mistake either way
+        let mut secrets = vec![Secret::new("key".to_string())];
+
+        assert!(load_secrets("nn_api_key", &mut secrets, Some(Path::new("key")), false).is_err());
+    }
+
+    #[test]
+    fn default_timing_follows_the_configured_cooldown() {
+        let tunables: Tunables = toml::from_str("cooldown_secs = 60").unwrap();
+
+        tunables.validate().unwrap();
+
+        assert_eq!(
+            tunables.timing(),
+            TimingProfile::for_cooldown(Duration::from_secs(60))
+        );
+        assert_ne!(
+            tunables.timing(),
+            TimingProfile::for_cooldown(DEFAULT_COOLDOWN)
+        );
+
+        // a default profile in the file wins
+        let tunables: Tunables = toml::from_str(
+            r#"
+            cooldown_secs = 60
+
+            [timing_profiles.default]
+            short = { kind = "uniform", min_ms = 0, max_ms = 1 }
+            long = { kind = "uniform", min_ms = 0, max_ms = 1 }
+            recheck = { kind = "uniform", min_ms = 0, max_ms = 1 }
+            pre_yoink = { kind = "uniform", min_ms = 0, max_ms = 1 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(tunables.timing(), tunables.timing_profiles["default"]);
+    }
+}
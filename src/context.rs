@@ -0,0 +1,87 @@
+use crate::{
+    admin::Control,
+    audit::AuditLog,
+    clock::ServerClock,
+    config::{Config, Tunables},
+    events::Events,
+    holds::HoldStore,
+    reputation::ReputationStore,
+    social::Profiles,
+};
+use anyhow::Context;
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+/// Everything that one running bot shares between its accounts and background tasks.
+///
+/// Built once when the bot starts and handed down so that none of it hides in a global.
+pub struct BotContext {
+    /// Cooldowns and the stats history are saved here.
+    pub data_dir: PathBuf,
+    pub clock: Arc<ServerClock>,
+    /// Lets the admin api pause, force and inspect the accounts.
+    pub control: Arc<Control>,
+    pub events: Events,
+    pub audit: AuditLog,
+    pub reputation: ReputationStore,
+    pub holds: HoldStore,
+    pub profiles: Profiles,
+    /// replaced when the config is reloaded
+    tunables: RwLock<Arc<Tunables>>,
+}
+
+impl BotContext {
+    /// Open the audit log and load everything that was saved in `config.data_dir`.
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        Ok(Self {
+            audit: AuditLog::open(&config.data_dir).context("opening the audit log")?,
+            reputation: ReputationStore::load(&config.data_dir).context("loading reputation")?,
+            holds: HoldStore::load(&config.data_dir).context("loading holds")?,
+            ..Self::in_memory(config)
+        })
+    }
+
+    /// Empty stores that are never saved. Audit records are dropped. For one-off commands and tests.
+    pub fn in_memory(config: &Config) -> Self {
+        Self {
+            data_dir: config.data_dir.clone(),
+            clock: Default::default(),
+            control: Default::default(),
+            events: Default::default(),
+            audit: Default::default(),
+            reputation: Default::default(),
+            holds: Default::default(),
+            profiles: Default::default(),
+            tunables: RwLock::new(Arc::new(config.tunables.clone())),
+        }
+    }
+
+    /// The current tunables. Grab them once per decision so that a reload can't change them halfway through.
+    pub fn tunables(&self) -> Arc<Tunables> {
+        self.tunables
+            .read()
+            .expect("tunables lock poisoned")
+            .clone()
+    }
+
+    /// Replace the tunables used by every part of the bot.
+    pub fn set_tunables(&self, tunables: Tunables) {
+        *self.tunables.write().expect("tunables lock poisoned") = Arc::new(tunables);
+
+        self.control.notify_changed();
+    }
+
+    /// Change some of the tunables. Holds the lock throughout so that concurrent updates don't undo each other.
+    pub fn update_tunables(&self, f: impl FnOnce(&mut Tunables)) {
+        {
+            let mut tunables = self.tunables.write().expect("tunables lock poisoned");
+
+            // anyone still holding the old tunables keeps them
+            f(Arc::make_mut(&mut tunables));
+        }
+
+        self.control.notify_changed();
+    }
+}
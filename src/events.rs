@@ -0,0 +1,209 @@
+use crate::{stats::Stats, team::Team};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+use tracing::{debug, info};
+
+/// Send this many failures in a row as [Event::RepeatedFailures].
+pub const FAILURE_ALERT_THRESHOLD: u32 = 3;
+
+/// Things that happened in the game that people want to hear about.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// One of our accounts took the flag.
+    Yoinked { account: String },
+    /// Someone took the flag from one of our accounts.
+    LostFlag {
+        account: String,
+        holder_id: String,
+        holder_name: String,
+    },
+    /// One of our accounts moved on the leaderboard. 1 is first.
+    RankChanged {
+        account: String,
+        old: usize,
+        new: usize,
+    },
+    /// We yoinked when we thought that our cooldown was over, but it wasn't.
+    RateLimited {
+        account: String,
+        until: Option<DateTime<Utc>>,
+    },
+    /// Yoinking keeps failing.
+    RepeatedFailures {
+        account: String,
+        failures: u32,
+        error: String,
+    },
+    /// Someone new has the flag.
+    HolderChanged {
+        holder_id: String,
+        holder_name: String,
+    },
+    /// The game updated the leaderboard. This happens about every 30 minutes.
+    StatsRefreshed { players: usize },
+    /// Everyone's time went down. A new season started.
+    SeasonReset,
+}
+
+impl Event {
+    /// Worth sending to a webhook. Game-wide events happen too often unless they are rare.
+    pub fn notify(&self) -> bool {
+        !matches!(
+            self,
+            Self::HolderChanged { .. } | Self::StatsRefreshed { .. }
+        )
+    }
+
+    /// A one line summary for chat.
+    pub fn message(&self) -> String {
+        match self {
+            Self::Yoinked { account } => format!("{} yoinked the flag!", account),
+            Self::LostFlag {
+                account,
+                holder_id,
+                holder_name,
+            } => format!(
+                "{} ({}) took the flag from {}",
+                holder_name, holder_id, account
+            ),
+            Self::RankChanged { account, old, new } if new < old => {
+                format!("{} moved up from #{} to #{}", account, old, new)
+            }
+            Self::RankChanged { account, old, new } => {
+                format!("{} dropped from #{} to #{}", account, old, new)
+            }
+            Self::RateLimited {
+                account,
+                until: Some(until),
+            } => format!("{} was rate limited until {}", account, until),
+            Self::RateLimited {
+                account,
+                until: None,
+            } => format!("{} was rate limited", account),
+            Self::RepeatedFailures {
+                account,
+                failures,
+                error,
+            } => format!(
+                "{} failed to yoink {} times in a row. last error: {}",
+                account, failures, error
+            ),
+            Self::HolderChanged {
+                holder_id,
+                holder_name,
+            } => format!("{} ({}) has the flag", holder_name, holder_id),
+            Self::StatsRefreshed { players } => {
+                format!("the leaderboard was refreshed. {} players", players)
+            }
+            Self::SeasonReset => "the season was reset".to_string(),
+        }
+    }
+}
+
+/// How many events a slow subscriber can fall behind before it starts missing them.
+const EVENTS_CAPACITY: usize = 256;
+
+/// Where events go. Clones share the same subscribers.
+#[derive(Clone)]
+pub struct Events {
+    tx: broadcast::Sender<Event>,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self {
+            tx: broadcast::channel(EVENTS_CAPACITY).0,
+        }
+    }
+}
+
+impl Events {
+    /// Tell every subscriber about an event. It is fine if nobody is listening.
+    pub fn emit(&self, event: Event) {
+        if event.notify() {
+            info!(?event, "{}", event.message());
+        } else {
+            debug!(?event, "{}", event.message());
+        }
+
+        let _ = self.tx.send(event);
+    }
+
+    /// Listen for events emitted after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.tx.subscribe()
+    }
+}
+
+/// Turns changes in the stats into events.
+#[derive(Default)]
+pub struct GameWatcher {
+    holder_id: Option<String>,
+    ranks: HashMap<String, usize>,
+    /// Everyone's time added up. Only a new season makes this go down.
+    total_secs: Option<u64>,
+}
+
+impl GameWatcher {
+    pub fn observe(&mut self, stats: &Stats, team: &Team, events: &Events) {
+        let flag = &stats.flag;
+
+        let total_secs = stats.user_times.values().sum::<u64>();
+
+        match self.total_secs.replace(total_secs) {
+            Some(old) if total_secs < old => {
+                // old ranks would all look like changes
+                self.ranks.clear();
+
+                events.emit(Event::SeasonReset);
+            }
+            Some(old) if total_secs != old => events.emit(Event::StatsRefreshed {
+                players: stats.user_times.len(),
+            }),
+            _ => {}
+        }
+
+        if let Some(old_holder_id) = self.holder_id.replace(flag.holder_id.clone()) {
+            if old_holder_id != flag.holder_id {
+                let holder_name = stats
+                    .users
+                    .get(&flag.holder_id)
+                    .unwrap_or(&flag.holder_name)
+                    .clone();
+
+                events.emit(Event::HolderChanged {
+                    holder_id: flag.holder_id.clone(),
+                    holder_name: holder_name.clone(),
+                });
+
+                if team.is_teammate(&old_holder_id) && !team.is_teammate(&flag.holder_id) {
+                    events.emit(Event::LostFlag {
+                        account: old_holder_id,
+                        holder_id: flag.holder_id.clone(),
+                        holder_name,
+                    });
+                }
+            }
+        }
+
+        for (rank, (user_id, _)) in stats.leaderboard().into_iter().enumerate() {
+            if !team.is_teammate(user_id) {
+                continue;
+            }
+
+            let new = rank + 1;
+
+            match self.ranks.insert(user_id.clone(), new) {
+                Some(old) if old != new => events.emit(Event::RankChanged {
+                    account: user_id.clone(),
+                    old,
+                    new,
+                }),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use crate::{
+    config::Tunables,
+    utils::{append_jsonl, load_jsonl},
+};
+use chrono::{DateTime, Duration, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+use tracing::{info, warn};
+
+/// Only this many finished holds are kept in memory. The file keeps everything.
+const HOLDS_KEEP: usize = 10_000;
+
+/// One time that someone had the flag from start to finish.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Hold {
+    pub holder_id: String,
+    pub started_at: DateTime<Utc>,
+    pub secs: u64,
+    /// how many recently active players were off cooldown and could have taken it when the hold started
+    pub available: usize,
+}
+
+/// What holding the flag is worth if we yoink at some time.
+#[derive(Clone, Copy, Debug)]
+pub struct HoldEstimate {
+    /// expected seconds until someone takes it from us
+    pub expected_secs: f64,
+    /// players that could take it
+    pub available: usize,
+}
+
+struct CurrentHold {
+    holder_id: String,
+    /// `None` if the hold started before we were watching
+    started_at: Option<DateTime<Utc>>,
+}
+
+/// Every hold we've watched. A survival model of how long holds last is fitted from them.
+#[derive(Default)]
+struct HoldLog {
+    /// `None` if finished holds aren't saved
+    path: Option<PathBuf>,
+    holds: VecDeque<Hold>,
+    current: Option<CurrentHold>,
+}
+
+/// The holds that the bot plays with. Shared by the stats loop that watches them and the strategies that read them.
+#[derive(Default)]
+pub struct HoldStore {
+    log: RwLock<HoldLog>,
+}
+
+/// Every finished hold is saved here (one JSON object per line).
+pub fn holds_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("holds.jsonl")
+}
+
+impl HoldStore {
+    /// Load `data_dir/holds.jsonl`. New holds are added to it.
+    pub fn load(data_dir: &Path) -> anyhow::Result<Self> {
+        let path = holds_path(data_dir);
+
+        let holds = load_jsonl(&path, HOLDS_KEEP)?;
+
+        info!(holds = holds.len(), "loaded holds");
+
+        Ok(Self {
+            log: RwLock::new(HoldLog {
+                path: Some(path),
+                holds,
+                current: None,
+            }),
+        })
+    }
+
+    /// Watch the flag holder. Called with every flag that the stats loop polls.
+    pub fn observe(&self, holder_id: &str, at: DateTime<Utc>, tunables: &Tunables) {
+        let mut log = self.log.write().expect("holds lock poisoned");
+
+        if let Err(err) = log.observe(holder_id, at, tunables) {
+            warn!(?err, "failed saving a hold");
+        }
+    }
+
+    /// What yoinking at `at` (now or later) is worth. `None` without enough holds to fit the model.
+    pub fn estimate(
+        &self,
+        at: DateTime<Utc>,
+        is_teammate: impl Fn(&str) -> bool,
+        tunables: &Tunables,
+    ) -> Option<HoldEstimate> {
+        self.log.read().expect("holds lock poisoned").estimate(
+            Utc::now(),
+            at,
+            is_teammate,
+            tunables,
+        )
+    }
+}
+
+impl HoldLog {
+    fn observe(
+        &mut self,
+        holder_id: &str,
+        at: DateTime<Utc>,
+        tunables: &Tunables,
+    ) -> anyhow::Result<()> {
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|x| x.holder_id == holder_id)
+        {
+            return Ok(());
+        }
+
+        let old = self.current.replace(CurrentHold {
+            holder_id: holder_id.to_string(),
+            // the first holder we see could have had the flag for a while
+            started_at: self.current.is_some().then_some(at),
+        });
+
+        let Some(CurrentHold {
+            holder_id,
+            started_at: Some(started_at),
+        }) = old
+        else {
+            return Ok(());
+        };
+
+        let hold = Hold {
+            available: self.available_at(started_at, started_at, |x| x == holder_id, tunables),
+            holder_id,
+            started_at,
+            secs: (at - started_at).num_seconds().max(0) as u64,
+        };
+
+        if self.holds.len() == HOLDS_KEEP {
+            self.holds.pop_front();
+        }
+
+        self.holds.push_back(hold);
+
+        match self.path.as_ref() {
+            Some(path) => append_jsonl(path, self.holds.back().expect("just pushed")),
+            None => Ok(()),
+        }
+    }
+
+    /// When each player last yoinked. Only players who yoinked in the active window before `now` are included.
+    fn last_yoinks(&self, now: DateTime<Utc>, tunables: &Tunables) -> HashMap<&str, DateTime<Utc>> {
+        let active_since = now - Duration::seconds(tunables.hold_value.active_window_secs as i64);
+
+        let current = self
+            .current
+            .as_ref()
+            .and_then(|x| Some((x.holder_id.as_str(), x.started_at?)));
+
+        self.holds
+            .iter()
+            .map(|x| (x.holder_id.as_str(), x.started_at))
+            .chain(current)
+            .filter(|(_, started_at)| active_since <= *started_at && *started_at <= now)
+            .fold(HashMap::new(), |mut acc, (holder_id, started_at)| {
+                let last = acc.entry(holder_id).or_insert(started_at);
+                *last = started_at.max(*last);
+                acc
+            })
+    }
+
+    /// Players active before `now` whose cooldowns are over at `at`. Everyone is assumed to have our cooldown.
+    fn available_at(
+        &self,
+        now: DateTime<Utc>,
+        at: DateTime<Utc>,
+        exclude: impl Fn(&str) -> bool,
+        tunables: &Tunables,
+    ) -> usize {
+        let cooldown = Duration::seconds(tunables.cooldown_secs as i64);
+
+        self.last_yoinks(now, tunables)
+            .into_iter()
+            .filter(|(holder_id, last)| !exclude(holder_id) && *last + cooldown <= at)
+            .count()
+    }
+
+    /// How often one available player takes the flag in each hour of the day. Per second.
+    ///
+    /// An exponential survival model per hour. Each hour is pulled toward the overall rate so that quiet hours with few holds don't swing wildly.
+    fn hazards(&self, tunables: &Tunables) -> Option<[f64; 24]> {
+        if self.holds.len() < tunables.hold_value.min_holds {
+            return None;
+        }
+
+        let mut taken = [0.0; 24];
+        let mut exposure = [0.0; 24];
+
+        for hold in self.holds.iter() {
+            let hour = hold.started_at.hour() as usize;
+
+            // every hold ended with someone taking the flag
+            taken[hour] += 1.0;
+            exposure[hour] += hold.secs.max(1) as f64 * hold.available.max(1) as f64;
+        }
+
+        let overall = taken.iter().sum::<f64>() / exposure.iter().sum::<f64>();
+
+        let prior = tunables.hold_value.prior_holds;
+
+        Some(std::array::from_fn(|hour| {
+            (taken[hour] + prior) / (exposure[hour] + prior / overall)
+        }))
+    }
+
+    fn estimate(
+        &self,
+        now: DateTime<Utc>,
+        at: DateTime<Utc>,
+        is_teammate: impl Fn(&str) -> bool,
+        tunables: &Tunables,
+    ) -> Option<HoldEstimate> {
+        let hazards = self.hazards(tunables)?;
+
+        // teammates never take the flag from us
+        let available = self.available_at(now, at, is_teammate, tunables);
+
+        let hazard = hazards[at.hour() as usize] * available.max(1) as f64;
+
+        Some(HoldEstimate {
+            expected_secs: 1.0 / hazard,
+            available,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tunables() -> Tunables {
+        let mut tunables = Tunables {
+            cooldown_secs: 600,
+            ..Default::default()
+        };
+
+        tunables.hold_value.active_window_secs = 3_600;
+        tunables.hold_value.min_holds = 4;
+        tunables.hold_value.prior_holds = 1.0;
+
+        tunables
+    }
+
+    /// Minutes after midnight on 2024-01-01.
+    fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+            + Duration::minutes(minutes)
+    }
+
+    fn hold(holder_id: &str, started_at: DateTime<Utc>, secs: u64) -> Hold {
+        Hold {
+            holder_id: holder_id.to_string(),
+            started_at,
+            secs,
+            available: 1,
+        }
+    }
+
+    /// Two short holds just after midnight and two long ones just after noon.
+    fn log() -> HoldLog {
+        HoldLog {
+            holds: [
+                hold("a", at(0), 100),
+                hold("b", at(10), 100),
+                hold("c", at(12 * 60), 300),
+                hold("d", at(12 * 60 + 10), 300),
+            ]
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn finished_holds_are_recorded() {
+        let tunables = tunables();
+        let mut log = HoldLog::default();
+
+        // we don't know when the first holder got it
+        log.observe("a", at(0), &tunables).unwrap();
+        log.observe("b", at(1), &tunables).unwrap();
+        log.observe("b", at(2), &tunables).unwrap();
+        log.observe("c", at(3), &tunables).unwrap();
+        log.observe("a", at(20), &tunables).unwrap();
+        log.observe("b", at(30), &tunables).unwrap();
+
+        let holds = log
+            .holds
+            .iter()
+            .map(|x| (x.holder_id.as_str(), x.secs, x.available))
+            .collect::<Vec<_>>();
+
+        // b and c were off cooldown when a took it back
+        assert_eq!(holds, [("b", 120, 0), ("c", 1_020, 0), ("a", 600, 2)]);
+    }
+
+    #[test]
+    fn hazards_need_enough_holds() {
+        let mut tunables = tunables();
+        tunables.hold_value.min_holds = 5;
+
+        assert!(log().hazards(&tunables).is_none());
+    }
+
+    #[test]
+    fn quiet_hours_get_the_overall_rate() {
+        let hazards = log().hazards(&tunables()).unwrap();
+
+        // 4 holds over 800 seconds overall. each hour starts with one hold's worth of that
+        assert_close(hazards[0], 3.0 / 400.0);
+        assert_close(hazards[12], 3.0 / 800.0);
+        assert_close(hazards[6], 1.0 / 200.0);
+    }
+
+    #[test]
+    fn estimates_count_available_players() {
+        let tunables = tunables();
+        let log = log();
+
+        let now = at(12 * 60 + 30);
+
+        // c and d are both off cooldown
+        let estimate = log.estimate(now, now, |_| false, &tunables).unwrap();
+        assert_eq!(estimate.available, 2);
+        assert_close(estimate.expected_secs, 800.0 / 3.0 / 2.0);
+
+        // teammates don't count
+        let estimate = log.estimate(now, now, |x| x == "d", &tunables).unwrap();
+        assert_eq!(estimate.available, 1);
+        assert_close(estimate.expected_secs, 800.0 / 3.0);
+
+        // an hour without holds uses the overall rate
+        let later = at(13 * 60);
+        let estimate = log.estimate(now, later, |_| false, &tunables).unwrap();
+        assert_close(estimate.expected_secs, 100.0);
+    }
+}
@@ -1,45 +1,73 @@
+mod admin;
+mod analytics;
+mod audit;
 #[allow(async_fn_in_trait)]
+mod backend;
+mod cli;
+mod clock;
+mod config;
+mod context;
+mod events;
+mod holds;
+mod prometheus;
+mod reputation;
+mod schedule;
+mod scheduler;
+mod secret;
 mod sleep;
+mod social;
 mod stats;
 mod strategy;
+mod supervisor;
+mod team;
 mod utils;
+mod webhook;
 mod yoinker;
 
+use crate::cli::Cli;
+use crate::config::Config;
 use crate::stats::Stats;
+use crate::supervisor::{cancel_on_signal, TaskFailed};
 use crate::utils::subtract_hashmaps;
 use anyhow::Context;
 use circular_buffer::CircularBuffer;
+use clap::Parser;
 use im::HashMap;
-use serde::Deserialize;
-use std::{env, sync::Arc, time::Duration};
-use tokio::sync::mpsc;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Instant;
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info};
-use utils::{https_client, init_logging};
+use utils::init_logging;
 
-/// The application name and version.
-pub static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+/// Something went wrong that doesn't have its own exit code.
+const EXIT_FAILURE: u8 = 1;
+
+/// The config is missing or invalid. From sysexits.h.
+const EXIT_CONFIG: u8 = 78;
+
+/// A background task crashed too many times. From sysexits.h.
+const EXIT_TASK_FAILED: u8 = 70;
+
+/// The config couldn't be loaded. Used to pick the exit code.
+#[derive(Debug)]
+struct ConfigError;
 
-/// TODO: this might change! we should get this from stats or something like that
-pub const COOLDOWN_TIME: Duration = Duration::from_secs(10 * 60);
-
-/// application configuration
-/// TODO: add Debug once this doesn't have secrets in it
-#[derive(Clone, Deserialize)]
-pub struct Config {
-    /// TODO: get this based on the signer/api key?
-    user_id: String,
-    cast_hash: String,
-    /// TODO: change this to `nn_api_key_file`
-    nn_api_key: String,
-    /// TODO: change this to `nn_signer_uuid_file`
-    nn_signer_uuid: String,
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("loading config")
+    }
 }
 
+/// The application name and version.
+pub static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+
 #[derive(Clone, Debug, Default)]
 pub struct State<const N: usize> {
     stats: CircularBuffer<N, Arc<Stats>>,
     diff: HashMap<String, u64>,
+    /// When the stats loop last confirmed that this is the latest state. `None` until the first stats.
+    checked_at: Option<Instant>,
 }
 
 impl<const N: usize> State<N> {
@@ -52,6 +80,19 @@ impl<const N: usize> State<N> {
         };
 
         if new_stats {
+            let total_secs = |x: &Stats| x.user_times.values().sum::<u64>();
+
+            if self
+                .stats
+                .back()
+                .is_some_and(|x| total_secs(&stats) < total_secs(x))
+            {
+                // a new season. the old stats would make every diff wrong
+                info!("the season was reset. clearing old stats");
+
+                self.stats.clear();
+            }
+
             self.stats.push_back(stats);
 
             match self.stats.len() {
@@ -73,73 +114,67 @@ impl<const N: usize> State<N> {
 
         new_stats
     }
+
+    /// Keep the newest flag holder without adding to the history. Returns true if the holder changed.
+    pub fn push_flag(&mut self, stats: &Arc<Stats>) -> bool {
+        match self.stats.back_mut() {
+            Some(back) if back.flag != stats.flag => {
+                *back = stats.clone();
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
 /// The entry point for the yoink bot.
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            // the same as returning the error from main, but the exit code says what kind of failure it was
+            eprintln!("Error: {:?}", err);
+
+            let code = if err.downcast_ref::<ConfigError>().is_some() {
+                EXIT_CONFIG
+            } else if err.downcast_ref::<TaskFailed>().is_some() {
+                EXIT_TASK_FAILED
+            } else {
+                EXIT_FAILURE
+            };
+
+            ExitCode::from(code)
+        }
+    }
+}
+
+async fn run() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
 
+    let cli = Cli::parse();
+
     // create all the cancellation tokens that we need to shut down the app gracefully
     let cancellation_token = CancellationToken::new();
 
-    let cancellation_guard = cancellation_token.clone().drop_guard();
-
     // configure the app
-    init_logging().context("init logging")?;
-
-    // listen for ctrl+c in the background
-    let ctrl_c_cancellation = cancellation_token.clone();
-    let _ctrl_c_handle = tokio::spawn(async move {
-        if let Err(err) = tokio::signal::ctrl_c().await {
-            error!(?err, "ctrl+c handler failed. shutting down");
-        } else {
-            info!("[ctrl+c] received. shutting down");
+    let _logging_guard = init_logging(cli.log_file()).context("init logging")?;
+
+    // listen for SIGINT and SIGTERM in the background
+    let signal_cancellation = cancellation_token.clone();
+    let _signal_handle = tokio::spawn(async move {
+        if let Err(err) = cancel_on_signal(signal_cancellation.clone()).await {
+            error!(?err, "signal handler failed. shutting down");
+            signal_cancellation.cancel();
         }
-        ctrl_c_cancellation.cancel();
     });
 
-    let config = envy::from_env::<Config>().context("loading config")?;
-
-    info!("Hello, {}! Ready to yoink their flags?!", config.user_id);
-
-    // create app components
-    let client = https_client().await?;
+    let config = Config::load().context(ConfigError)?;
 
-    let (app_state_tx, app_state_rx) = mpsc::unbounded_channel();
+    info!(?config, "loaded config");
 
-    // create app futures
-    let yoinker_stat_loop_f = stats::stats_loop::<12>(
-        app_state_tx,
-        cancellation_token.clone(),
-        client.clone(),
-        config.clone(),
-    );
-    let yoinker_main_loop_f =
-        yoinker::main_loop(app_state_rx, cancellation_token, &client, &config);
-
-    // spawn background workers
-    let yoinker_stats_handle = tokio::spawn(yoinker_stat_loop_f);
-
-    // run the main app
-    // TODO: join multiple futures here? don't try join because we want them all to have time to gracefully shut down
-    let exit = yoinker_main_loop_f.await.context("yoinker main loop");
-
-    // graceful shutdown
-    drop(cancellation_guard);
-
-    // TODO: we should probably just abort it. but i like checking for errors
-    if let Err(err) = yoinker_stats_handle
+    cli.command
+        .unwrap_or_default()
+        .run(config, cancellation_token)
         .await
-        .expect("yoinker_stats_handle shouldn't ever have a join error")
-    {
-        error!(?err, "yoinker stats loop failed");
-        // TODO: should this cause the main app to exit non-zero? maybe override exit if it isn't already an error. if it is an error, just log here
-    }
-
-    exit?;
-
-    info!("exited successfully");
-
-    Ok(())
 }
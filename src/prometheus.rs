@@ -0,0 +1,130 @@
+use crate::{stats::Stats, team::Team};
+use anyhow::Context;
+use chrono::Utc;
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, Unit};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+/// Serve our metrics for Prometheus on `addr`. Until this is called, recording metrics does nothing.
+pub fn install(addr: SocketAddr) -> anyhow::Result<()> {
+    PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .set_buckets(&[0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0])?
+        .install()
+        .context("installing the prometheus exporter")?;
+
+    describe_counter!(
+        "yoinker_yoink_attempts_total",
+        "frame actions sent. labeled by account and outcome"
+    );
+    describe_counter!(
+        "yoinker_rate_limited_total",
+        "yoinks that were rejected because our cooldown wasn't over"
+    );
+    describe_counter!(
+        "yoinker_unconfirmed_yoinks_total",
+        "frame actions that went through but the flag never showed us as the holder. labeled by account"
+    );
+    describe_counter!(
+        "yoinker_flag_held_seconds_total",
+        Unit::Seconds,
+        "time that we have seen one of our accounts holding the flag"
+    );
+    describe_counter!(
+        "yoinker_decisions_total",
+        "strategy decisions. labeled by strategy and decision"
+    );
+    describe_counter!(
+        "yoinker_task_restarts_total",
+        "background tasks that crashed and were restarted. labeled by task"
+    );
+    describe_gauge!(
+        "yoinker_user_time_seconds",
+        Unit::Seconds,
+        "our accounts' total time holding the flag according to the game"
+    );
+    describe_gauge!(
+        "yoinker_rank",
+        "our accounts' places on the leaderboard. 1 is first"
+    );
+    describe_gauge!(
+        "yoinker_flag_holder_fid",
+        "fid of the current flag holder. 0 if they aren't on farcaster"
+    );
+    describe_gauge!(
+        "yoinker_flag_holder_ours",
+        "1 while one of our accounts has the flag"
+    );
+    describe_gauge!(
+        "yoinker_stats_last_success_timestamp_seconds",
+        Unit::Seconds,
+        "unix time of the last successful stats fetch. subtract from time() for its age"
+    );
+    describe_histogram!(
+        "yoinker_request_duration_seconds",
+        Unit::Seconds,
+        "game api request latency. labeled by endpoint"
+    );
+
+    Ok(())
+}
+
+/// Turns our view of the game into metrics. Updated every time that the stats loop gets new stats.
+#[derive(Default)]
+pub struct StatsMetrics {
+    /// The holder at the last update and when we saw them.
+    holder: Option<(String, Instant)>,
+}
+
+impl StatsMetrics {
+    pub fn record(&mut self, stats: &Stats, team: &Team) {
+        let now = Instant::now();
+
+        gauge!("yoinker_stats_last_success_timestamp_seconds").set(Utc::now().timestamp() as f64);
+
+        let flag = &stats.flag;
+
+        let seen_at = match self.holder.take() {
+            Some((holder_id, seen_at)) if holder_id == flag.holder_id => {
+                if team.is_teammate(&holder_id) {
+                    let held = (now - seen_at).as_secs();
+
+                    counter!("yoinker_flag_held_seconds_total", "account" => holder_id)
+                        .increment(held);
+
+                    // keep the leftover fraction of a second for the next update
+                    seen_at + Duration::from_secs(held)
+                } else {
+                    now
+                }
+            }
+            _ => now,
+        };
+
+        // unlabeled so that every new holder doesn't add a series
+        let fid = flag
+            .holder_id
+            .strip_prefix("farcaster:")
+            .and_then(|x| x.parse::<u64>().ok())
+            .unwrap_or_default();
+
+        gauge!("yoinker_flag_holder_fid").set(fid as f64);
+        gauge!("yoinker_flag_holder_ours").set(if team.is_teammate(&flag.holder_id) {
+            1.0
+        } else {
+            0.0
+        });
+
+        self.holder = Some((flag.holder_id.clone(), seen_at));
+
+        for (rank, (user_id, secs)) in stats.leaderboard().into_iter().enumerate() {
+            if team.is_teammate(user_id) {
+                gauge!("yoinker_user_time_seconds", "account" => user_id.clone()).set(secs as f64);
+                gauge!("yoinker_rank", "account" => user_id.clone()).set((rank + 1) as f64);
+            }
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use crate::{config::ReputationTunables, context::BotContext, events::Event};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Everyone who has taken the flag from us. Saved so that grudges survive a restart.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Reputation {
+    pub opponents: BTreeMap<String, Opponent>,
+}
+
+/// How aggressive one player has been toward us.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Opponent {
+    pub name: String,
+    /// decays over time. read it with [Opponent::score_at]
+    score: f64,
+    /// when `score` was last changed
+    updated_at: DateTime<Utc>,
+    /// how many times they took the flag from us
+    pub yoinks: u64,
+    pub last_yoink_at: DateTime<Utc>,
+    /// the shortest time that we held the flag before they took it. `None` if we never knew when we got it
+    pub fastest_secs: Option<u64>,
+}
+
+impl Opponent {
+    /// The score decayed to `at`.
+    pub fn score_at(&self, at: DateTime<Utc>, tunables: &ReputationTunables) -> f64 {
+        let elapsed = (at - self.updated_at).num_milliseconds().max(0) as f64 / 1000.0;
+
+        self.score * 0.5f64.powf(elapsed / tunables.half_life_secs as f64)
+    }
+}
+
+/// The file that holds the reputation store.
+pub fn reputation_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("reputation.json")
+}
+
+impl Reputation {
+    /// An empty store if nothing has been saved yet.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = match std::fs::read(path) {
+            Ok(x) => x,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+        };
+
+        serde_json::from_slice(&contents).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Write to a temporary file first so that a crash can't leave half a file behind.
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+
+        let tmp = path.with_extension("json.tmp");
+
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("writing {}", tmp.display()))?;
+
+        std::fs::rename(&tmp, path).with_context(|| format!("renaming {}", tmp.display()))
+    }
+
+    /// The decayed score of a player. 0 for players who have never taken the flag from us.
+    pub fn score(&self, user_id: &str, at: DateTime<Utc>, tunables: &ReputationTunables) -> f64 {
+        self.opponents
+            .get(user_id)
+            .map(|x| x.score_at(at, tunables))
+            .unwrap_or_default()
+    }
+
+    /// Someone took the flag from us. `held_secs` is how long we had it, if we know.
+    pub fn record(
+        &mut self,
+        holder_id: &str,
+        holder_name: &str,
+        held_secs: Option<u64>,
+        at: DateTime<Utc>,
+        tunables: &ReputationTunables,
+    ) -> f64 {
+        // taking it right back is worse than taking it after we had a turn
+        let weight = match held_secs {
+            Some(x) if x <= tunables.quick_secs => tunables.quick_weight,
+            _ => 1.0,
+        };
+
+        let score = self.score(holder_id, at, tunables) + weight;
+
+        let opponent = self
+            .opponents
+            .entry(holder_id.to_string())
+            .or_insert_with(|| Opponent {
+                name: holder_name.to_string(),
+                score: 0.0,
+                updated_at: at,
+                yoinks: 0,
+                last_yoink_at: at,
+                fastest_secs: None,
+            });
+
+        opponent.name = holder_name.to_string();
+        opponent.score = score;
+        opponent.updated_at = at;
+        opponent.yoinks += 1;
+        opponent.last_yoink_at = at;
+        opponent.fastest_secs = match (opponent.fastest_secs, held_secs) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        score
+    }
+}
+
+/// The reputation that the bot plays with. Saved after every change if it was loaded from a file.
+#[derive(Default)]
+pub struct ReputationStore {
+    path: Option<PathBuf>,
+    reputation: RwLock<Reputation>,
+}
+
+impl ReputationStore {
+    /// Load `data_dir/reputation.json`.
+    pub fn load(data_dir: &Path) -> anyhow::Result<Self> {
+        let path = reputation_path(data_dir);
+
+        let reputation = Reputation::load(&path)?;
+
+        info!(opponents = reputation.opponents.len(), "loaded reputation");
+
+        Ok(Self {
+            path: Some(path),
+            reputation: RwLock::new(reputation),
+        })
+    }
+
+    /// A player's decayed score right now.
+    pub fn score(&self, user_id: &str, tunables: &ReputationTunables) -> f64 {
+        self.reputation
+            .read()
+            .expect("reputation lock poisoned")
+            .score(user_id, Utc::now(), tunables)
+    }
+
+    /// [Reputation::record] and then save.
+    fn record(
+        &self,
+        holder_id: &str,
+        holder_name: &str,
+        held_secs: Option<u64>,
+        at: DateTime<Utc>,
+        tunables: &ReputationTunables,
+    ) -> f64 {
+        let mut reputation = self.reputation.write().expect("reputation lock poisoned");
+
+        let score = reputation.record(holder_id, holder_name, held_secs, at, tunables);
+
+        if let Some(path) = self.path.as_ref() {
+            if let Err(err) = reputation.save(path) {
+                warn!(?err, "failed saving reputation");
+            }
+        }
+
+        score
+    }
+}
+
+/// Update reputations from the flag changes that the stats loop emits.
+pub async fn reputation_loop(
+    ctx: Arc<BotContext>,
+    cancellation_token: CancellationToken,
+) -> anyhow::Result<()> {
+    let mut events = ctx.events.subscribe();
+
+    // when each of our accounts got the flag. the gap until they lose it tells us how impatient the taker was
+    let mut got_flag_at = HashMap::<String, DateTime<Utc>>::new();
+
+    loop {
+        let event = tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            x = events.recv() => match x {
+                Ok(x) => x,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "reputation fell behind. some events were dropped");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        match event {
+            Event::Yoinked { account, .. } => {
+                got_flag_at.insert(account, Utc::now());
+            }
+            Event::LostFlag {
+                account,
+                holder_id,
+                holder_name,
+            } => {
+                let now = Utc::now();
+
+                let held_secs = got_flag_at
+                    .remove(&account)
+                    .map(|x| (now - x).num_seconds().max(0) as u64);
+
+                let score = ctx.reputation.record(
+                    &holder_id,
+                    &holder_name,
+                    held_secs,
+                    now,
+                    &ctx.tunables().reputation,
+                );
+
+                info!(
+                    holder_id,
+                    holder_name, held_secs, score, "reputation changed"
+                );
+            }
+            Event::SeasonReset => {
+                got_flag_at.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    fn tunables() -> ReputationTunables {
+        ReputationTunables {
+            half_life_secs: 3_600,
+            quick_secs: 60,
+            quick_weight: 3.0,
+        }
+    }
+
+    #[test]
+    fn scores_halve_every_half_life() {
+        let tunables = tunables();
+        let at = DateTime::UNIX_EPOCH;
+
+        let mut reputation = Reputation::default();
+
+        assert_eq!(reputation.score("farcaster:1", at, &tunables), 0.0);
+
+        reputation.record("farcaster:1", "alice", None, at, &tunables);
+
+        assert_eq!(reputation.score("farcaster:1", at, &tunables), 1.0);
+        assert_eq!(
+            reputation.score("farcaster:1", at + TimeDelta::hours(2), &tunables),
+            0.25
+        );
+
+        // scores don't grow when read before they were updated
+        assert_eq!(
+            reputation.score("farcaster:1", at - TimeDelta::hours(1), &tunables),
+            1.0
+        );
+    }
+
+    #[test]
+    fn quick_yoinks_count_extra() {
+        let tunables = tunables();
+        let at = DateTime::UNIX_EPOCH;
+
+        let mut reputation = Reputation::default();
+
+        assert_eq!(
+            reputation.record("farcaster:1", "alice", Some(60), at, &tunables),
+            3.0
+        );
+        assert_eq!(
+            reputation.record("farcaster:2", "bob", Some(61), at, &tunables),
+            1.0
+        );
+    }
+
+    #[test]
+    fn records_add_to_the_decayed_score() {
+        let tunables = tunables();
+        let at = DateTime::UNIX_EPOCH;
+
+        let mut reputation = Reputation::default();
+
+        reputation.record("farcaster:1", "alice", Some(600), at, &tunables);
+        reputation.record(
+            "farcaster:1",
+            "alice",
+            None,
+            at + TimeDelta::hours(1),
+            &tunables,
+        );
+
+        let score = reputation.record(
+            "farcaster:1",
+            "alice2",
+            Some(300),
+            at + TimeDelta::hours(2),
+            &tunables,
+        );
+
+        // (1 / 2 + 1) / 2 + 1
+        assert_eq!(score, 1.75);
+
+        let opponent = &reputation.opponents["farcaster:1"];
+
+        assert_eq!(opponent.name, "alice2");
+        assert_eq!(opponent.yoinks, 3);
+        assert_eq!(opponent.last_yoink_at, at + TimeDelta::hours(2));
+        // a yoink without a hold time doesn't replace the fastest one
+        assert_eq!(opponent.fastest_secs, Some(300));
+    }
+}
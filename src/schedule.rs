@@ -0,0 +1,208 @@
+use crate::strategy::{Decision, YoinkDecision};
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// How hard to play. Ordered from least to most.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Intensity {
+    /// don't yoink at all. the admin api can still force a yoink
+    Off,
+    /// only snipe targets. no impatient yoinks
+    Passive,
+    #[default]
+    Normal,
+    /// every yoink fires the moment our cooldown ends. never be nice
+    Aggressive,
+}
+
+/// When to play and how hard. Windows are checked in order and the first match wins.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Schedule {
+    pub timezone: Tz,
+    /// used outside of every window
+    pub default: Intensity,
+    pub windows: Vec<ScheduleWindow>,
+}
+
+/// A weekly window in the schedule's time zone.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleWindow {
+    /// the days that the window starts on. empty means every day
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    /// if this is before `start`, the window runs past midnight into the next day
+    pub end: NaiveTime,
+    pub intensity: Intensity,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            default: Intensity::Normal,
+            windows: vec![],
+        }
+    }
+}
+
+impl Schedule {
+    pub fn intensity_at(&self, at: DateTime<Utc>) -> Intensity {
+        let local = at.with_timezone(&self.timezone);
+
+        let (day, time) = (local.weekday(), local.time());
+
+        self.windows
+            .iter()
+            .find(|x| x.contains(day, time))
+            .map(|x| x.intensity)
+            .unwrap_or(self.default)
+    }
+
+    pub fn intensity_now(&self) -> Intensity {
+        self.intensity_at(Utc::now())
+    }
+
+    /// A window that starts and ends at the same time is empty. Reject it instead of silently never matching.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (i, window) in self.windows.iter().enumerate() {
+            if window.start == window.end {
+                anyhow::bail!(
+                    "tunables.schedule.windows[{}] starts and ends at {}. leave out `days` for a window that lasts all day",
+                    i,
+                    window.start
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ScheduleWindow {
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    fn contains(&self, day: Weekday, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.starts_on(day) && self.start <= time && time < self.end
+        } else {
+            // past midnight. the evening belongs to today and the morning belongs to yesterday's window
+            (self.starts_on(day) && self.start <= time)
+                || (self.starts_on(day.pred()) && time < self.end)
+        }
+    }
+}
+
+impl Intensity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Passive => "passive",
+            Self::Normal => "normal",
+            Self::Aggressive => "aggressive",
+        }
+    }
+
+    /// Change a strategy's decision to match how hard we are playing. Targets and draws are kept for the audit log.
+    pub fn apply(&self, decision: Decision) -> Decision {
+        match (self, decision.decision) {
+            (Self::Off, YoinkDecision::Yoink | YoinkDecision::Snipe) => {
+                decision.overruled(YoinkDecision::Wait, "off hours")
+            }
+            (Self::Passive, YoinkDecision::Yoink) => {
+                decision.overruled(YoinkDecision::Wait, "passive hours")
+            }
+            (Self::Aggressive, YoinkDecision::Yoink) => {
+                decision.overruled(YoinkDecision::Snipe, "aggressive hours")
+            }
+            _ => decision,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn time(hour: u32, min: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, min, 0).unwrap()
+    }
+
+    fn overnight(days: Vec<Weekday>) -> ScheduleWindow {
+        ScheduleWindow {
+            days,
+            start: time(22, 0),
+            end: time(6, 0),
+            intensity: Intensity::Off,
+        }
+    }
+
+    #[test]
+    fn window_runs_past_midnight() {
+        let window = overnight(vec![]);
+
+        assert!(window.contains(Weekday::Mon, time(23, 0)));
+        assert!(window.contains(Weekday::Tue, time(1, 0)));
+        assert!(!window.contains(Weekday::Tue, time(6, 0)));
+        assert!(!window.contains(Weekday::Tue, time(12, 0)));
+    }
+
+    #[test]
+    fn morning_belongs_to_the_day_the_window_started() {
+        let window = overnight(vec![Weekday::Fri]);
+
+        assert!(window.contains(Weekday::Fri, time(23, 0)));
+        assert!(window.contains(Weekday::Sat, time(1, 0)));
+
+        // friday morning is thursday night
+        assert!(!window.contains(Weekday::Fri, time(1, 0)));
+        assert!(!window.contains(Weekday::Sat, time(23, 0)));
+    }
+
+    #[test]
+    fn first_matching_window_wins() {
+        let schedule = Schedule {
+            timezone: Tz::UTC,
+            default: Intensity::Normal,
+            windows: vec![
+                overnight(vec![]),
+                ScheduleWindow {
+                    days: vec![],
+                    start: time(0, 0),
+                    end: time(12, 0),
+                    intensity: Intensity::Aggressive,
+                },
+            ],
+        };
+
+        // 2024-01-01 was a monday
+        let at = |hour| Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap();
+
+        assert_eq!(schedule.intensity_at(at(3)), Intensity::Off);
+        assert_eq!(schedule.intensity_at(at(9)), Intensity::Aggressive);
+        assert_eq!(schedule.intensity_at(at(15)), Intensity::Normal);
+    }
+
+    #[test]
+    fn windows_follow_daylight_saving_time() {
+        let schedule = Schedule {
+            timezone: chrono_tz::America::New_York,
+            default: Intensity::Normal,
+            windows: vec![overnight(vec![])],
+        };
+
+        // 10:30 UTC is 05:30 EST the day before the clocks change and 06:30 EDT the day they do
+        let before = Utc.with_ymd_and_hms(2024, 3, 9, 10, 30, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2024, 3, 10, 10, 30, 0).unwrap();
+
+        assert_eq!(schedule.intensity_at(before), Intensity::Off);
+        assert_eq!(schedule.intensity_at(after), Intensity::Normal);
+    }
+}
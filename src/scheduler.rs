@@ -0,0 +1,137 @@
+use crate::{backend::YoinkOutcome, clock::ServerClock};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+/// Arrive this long after the cooldown expires. Arriving early wastes an attempt on a rate limit.
+pub const FIRE_MARGIN: Duration = Duration::from_millis(50);
+
+/// Tracks exactly when our cooldown expires so that we can yoink the moment that it does.
+pub struct FireScheduler {
+    clock: Arc<ServerClock>,
+    /// When our cooldown expires. In server time.
+    cooldown_ends_at: Option<DateTime<Utc>>,
+    /// Where `cooldown_ends_at` is saved so that it survives a restart.
+    path: PathBuf,
+    /// When the impatient path yoinks no matter what the strategy says. Right away until [FireScheduler::delay_impatience] is called.
+    /// TODO: save this too so that we can recover from a restart
+    impatient_at: Instant,
+}
+
+/// The file that holds an account's cooldown.
+pub fn cooldown_path(data_dir: &Path, user_id: &str) -> PathBuf {
+    data_dir
+        .join("cooldowns")
+        .join(user_id.replace(|x: char| !x.is_ascii_alphanumeric(), "_"))
+}
+
+/// When an account's cooldown ends in server time. `None` if we haven't saved one.
+pub fn load_cooldown(path: &Path) -> anyhow::Result<Option<DateTime<Utc>>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+    };
+
+    let cooldown_ends_at = DateTime::parse_from_rfc3339(contents.trim())
+        .with_context(|| format!("parsing {}", path.display()))?
+        .with_timezone(&Utc);
+
+    Ok(Some(cooldown_ends_at))
+}
+
+fn save_cooldown(path: &Path, cooldown_ends_at: DateTime<Utc>) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating {}", parent.display()))?;
+    }
+
+    std::fs::write(path, cooldown_ends_at.to_rfc3339())
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+impl FireScheduler {
+    /// Resume from the cooldown saved in `data_dir` (if any).
+    pub fn new(clock: Arc<ServerClock>, data_dir: &Path, user_id: &str) -> Self {
+        let path = cooldown_path(data_dir, user_id);
+
+        let cooldown_ends_at = load_cooldown(&path).unwrap_or_else(|err| {
+            warn!(?err, "ignoring saved cooldown");
+            None
+        });
+
+        if let Some(cooldown_ends_at) = cooldown_ends_at {
+            info!(%cooldown_ends_at, "resuming saved cooldown");
+        }
+
+        Self {
+            clock,
+            cooldown_ends_at,
+            path,
+            impatient_at: Instant::now(),
+        }
+    }
+
+    /// Update our cooldown from the result of a yoink.
+    pub fn record(&mut self, outcome: &YoinkOutcome, cooldown: Duration) {
+        match outcome {
+            YoinkOutcome::Yoinked { at, .. } => {
+                self.cooldown_ends_at = Some(*at + cooldown);
+            }
+            YoinkOutcome::RateLimited { until: Some(until) } => {
+                self.cooldown_ends_at = Some(*until);
+            }
+            YoinkOutcome::RateLimited { until: None } => {
+                // we don't know when it ends. assume a full cooldown from now
+                self.cooldown_ends_at = Some(self.clock.server_now() + cooldown);
+            }
+            YoinkOutcome::Unknown { .. } => {}
+        }
+
+        debug!(cooldown_ends_at = ?self.cooldown_ends_at, "scheduled");
+
+        if let Some(cooldown_ends_at) = self.cooldown_ends_at {
+            if let Err(err) = save_cooldown(&self.path, cooldown_ends_at) {
+                warn!(?err, "failed saving cooldown");
+            }
+        }
+    }
+
+    /// When our cooldown expires. In server time. `None` if we don't know of one.
+    pub fn cooldown_ends_at(&self) -> Option<DateTime<Utc>> {
+        self.cooldown_ends_at
+    }
+
+    pub fn impatient_at(&self) -> Instant {
+        self.impatient_at
+    }
+
+    /// True once we've waited too long for the strategy to yoink.
+    pub fn is_impatient(&self) -> bool {
+        Instant::now() > self.impatient_at
+    }
+
+    /// Don't get impatient again until `delay` from now.
+    pub fn delay_impatience(&mut self, delay: Duration) {
+        self.impatient_at = Instant::now() + delay;
+    }
+
+    /// The local instant to send a request so that it arrives just after our cooldown expires.
+    ///
+    /// `None` if we aren't in a cooldown.
+    pub fn fire_at(&self) -> Option<Instant> {
+        let cooldown_ends_at = self.cooldown_ends_at?;
+
+        let send_at = self.clock.to_local(cooldown_ends_at) - self.clock.latency() + FIRE_MARGIN;
+
+        let wait = (send_at - Utc::now()).to_std().ok()?;
+
+        Some(Instant::now() + wait)
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::Context;
+use serde::Deserialize;
+use std::{fmt, path::Path};
+
+/// A value that must never end up in logs. Debug and Display are redacted.
+#[derive(Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(x: T) -> Self {
+        Self(x)
+    }
+
+    /// Get the actual value. Be careful where it goes!
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+/// Read a list of secrets from a file. Values can be separated by commas or newlines.
+///
+/// This is how Docker and Kubernetes hand secrets to a container.
+pub fn read_secrets_file(path: &Path) -> anyhow::Result<Vec<Secret<String>>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("reading secrets from {}", path.display()))?;
+
+    let secrets = contents
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| Secret::new(x.to_string()))
+        .collect::<Vec<_>>();
+
+    if secrets.is_empty() {
+        anyhow::bail!("{} is empty", path.display());
+    }
+
+    Ok(secrets)
+}
@@ -1,10 +1,13 @@
-use crate::COOLDOWN_TIME;
 use nanorand::Rng;
+use serde::Deserialize;
 use std::time::Duration;
 use tokio::{select, time::sleep};
 use tokio_util::sync::CancellationToken;
 use tracing::info;
 
+/// How many times a truncated normal is redrawn before giving up and clamping.
+const TRUNCATED_NORMAL_TRIES: usize = 16;
+
 /// this keeps us from wasting time when we want to shut down
 /// TODO: helper that sleeps a random duration?
 pub async fn sleep_with_cancel(cancellation_token: &CancellationToken, duration: Duration) {
@@ -14,22 +17,186 @@ pub async fn sleep_with_cancel(cancellation_token: &CancellationToken, duration:
     };
 }
 
-#[tracing::instrument]
-pub fn short_jitter() -> Duration {
-    let max_short = (COOLDOWN_TIME / 10).as_millis() as u64;
+/// Every random delay that the bot uses. Pick one with `tunables.timing_profile`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TimingProfile {
+    /// retrying soon after something failed
+    pub short: Distribution,
+    /// backing off after something worked
+    pub long: Distribution,
+    /// strategies waiting before they check the flag again
+    pub recheck: Distribution,
+    /// strategies waiting right before they yoink
+    pub pre_yoink: Distribution,
+}
+
+/// A random delay in milliseconds. Every kind has a `max_ms` so that one unlucky draw can't stall the bot.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Distribution {
+    Uniform {
+        min_ms: u64,
+        max_ms: u64,
+    },
+    /// lots of short delays and a few long ones
+    Exponential {
+        mean_ms: u64,
+        max_ms: u64,
+    },
+    /// a long tail. this is what human reaction times look like
+    LogNormal {
+        median_ms: u64,
+        sigma: f64,
+        max_ms: u64,
+    },
+    TruncatedNormal {
+        mean_ms: u64,
+        std_dev_ms: u64,
+        min_ms: u64,
+        max_ms: u64,
+    },
+}
+
+impl TimingProfile {
+    /// Retries wait up to a tenth of the cooldown and backoffs up to half of it.
+    pub fn for_cooldown(cooldown: Duration) -> Self {
+        Self {
+            short: Distribution::Uniform {
+                min_ms: 0,
+                max_ms: (cooldown / 10).as_millis() as u64,
+            },
+            long: Distribution::Uniform {
+                min_ms: 0,
+                max_ms: (cooldown / 2).as_millis() as u64,
+            },
+            recheck: Distribution::Uniform {
+                min_ms: 500,
+                max_ms: 2_000,
+            },
+            pre_yoink: Distribution::Uniform {
+                min_ms: 0,
+                max_ms: 3_000,
+            },
+        }
+    }
+
+    /// Check every distribution in the profile. `name` is used in the errors.
+    pub fn validate(&self, name: &str) -> anyhow::Result<()> {
+        for (field, x) in [
+            ("short", &self.short),
+            ("long", &self.long),
+            ("recheck", &self.recheck),
+            ("pre_yoink", &self.pre_yoink),
+        ] {
+            x.validate().map_err(|err| {
+                anyhow::anyhow!("tunables.timing_profiles.{}.{}: {}", name, field, err)
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Distribution {
+    /// Ranges can't be upside down and the shape parameters have to be positive.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match *self {
+            Self::Uniform { min_ms, max_ms } | Self::TruncatedNormal { min_ms, max_ms, .. } => {
+                if min_ms > max_ms {
+                    anyhow::bail!("min_ms ({}) must be <= max_ms ({})", min_ms, max_ms);
+                }
+            }
+            Self::Exponential { mean_ms, .. } => {
+                if mean_ms == 0 {
+                    anyhow::bail!("mean_ms must be > 0");
+                }
+            }
+            Self::LogNormal {
+                median_ms, sigma, ..
+            } => {
+                if median_ms == 0 {
+                    anyhow::bail!("median_ms must be > 0");
+                }
+                if !sigma.is_finite() || sigma <= 0.0 {
+                    anyhow::bail!("sigma ({}) must be > 0", sigma);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draw a delay in milliseconds.
+    pub fn sample_ms(&self) -> u64 {
+        let mut rng = nanorand::tls_rng();
+
+        let ms = match *self {
+            Self::Uniform { min_ms, max_ms } => return rng.generate_range(min_ms..=max_ms),
+            Self::Exponential { mean_ms, .. } => {
+                // 1 - x so that we never take ln(0)
+                -(mean_ms as f64) * (1.0 - unit(&mut rng)).ln()
+            }
+            Self::LogNormal {
+                median_ms, sigma, ..
+            } => median_ms as f64 * (sigma * standard_normal(&mut rng)).exp(),
+            Self::TruncatedNormal {
+                mean_ms,
+                std_dev_ms,
+                min_ms,
+                max_ms,
+            } => {
+                let draw = || mean_ms as f64 + std_dev_ms as f64 * standard_normal(&mut rng);
+
+                // redraw instead of clamping so that min and max don't pile up. clamp if the window is way off the mean
+                std::iter::repeat_with(draw)
+                    .take(TRUNCATED_NORMAL_TRIES)
+                    .find(|x| (min_ms as f64..=max_ms as f64).contains(x))
+                    .unwrap_or(mean_ms as f64)
+                    .clamp(min_ms as f64, max_ms as f64)
+            }
+        };
+
+        // `as` saturates. negative and NaN become 0
+        (ms as u64).min(self.max_ms())
+    }
+
+    fn max_ms(&self) -> u64 {
+        match *self {
+            Self::Uniform { max_ms, .. }
+            | Self::Exponential { max_ms, .. }
+            | Self::LogNormal { max_ms, .. }
+            | Self::TruncatedNormal { max_ms, .. } => max_ms,
+        }
+    }
+}
+
+/// Uniform in `[0, 1)`.
+fn unit(rng: &mut impl Rng<8>) -> f64 {
+    // the top 53 bits fill an f64's mantissa exactly
+    (rng.generate::<u64>() >> 11) as f64 / (1u64 << 53) as f64
+}
 
-    let ms = nanorand::tls_rng().generate_range(0..=max_short);
+/// Mean 0 and standard deviation 1. Box-Muller.
+fn standard_normal(rng: &mut impl Rng<8>) -> f64 {
+    let u1 = 1.0 - unit(rng);
+    let u2 = unit(rng);
+
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+#[tracing::instrument(skip_all)]
+pub fn short_jitter(timing: &TimingProfile) -> Duration {
+    let ms = timing.short.sample_ms();
 
     info!(ms);
 
     Duration::from_millis(ms)
 }
 
-#[tracing::instrument]
-pub fn long_jitter() -> Duration {
-    let max_long = (COOLDOWN_TIME / 2).as_millis() as u64;
-
-    let ms = nanorand::tls_rng().generate_range(0..=max_long);
+#[tracing::instrument(skip_all)]
+pub fn long_jitter(timing: &TimingProfile) -> Duration {
+    let ms = timing.long.sample_ms();
 
     info!(ms);
 
@@ -37,16 +204,126 @@ pub fn long_jitter() -> Duration {
 }
 
 #[inline]
-pub async fn sleep_short_jitter(cancellation_token: &CancellationToken) {
-    sleep_with_cancel(cancellation_token, short_jitter()).await;
+pub async fn sleep_short_jitter(cancellation_token: &CancellationToken, timing: &TimingProfile) {
+    sleep_with_cancel(cancellation_token, short_jitter(timing)).await;
 }
 
 #[inline]
-pub async fn sleep_long_jitter(cancellation_token: &CancellationToken) {
-    sleep_with_cancel(cancellation_token, long_jitter()).await;
+pub async fn sleep_long_jitter(cancellation_token: &CancellationToken, timing: &TimingProfile) {
+    sleep_with_cancel(cancellation_token, long_jitter(timing)).await;
 }
 
-#[inline]
-pub async fn sleep_cooldown_jitter(cancellation_token: &CancellationToken) {
-    sleep_with_cancel(cancellation_token, COOLDOWN_TIME + short_jitter()).await;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DRAWS: usize = 10_000;
+
+    fn draws(x: &Distribution) -> Vec<u64> {
+        std::iter::repeat_with(|| x.sample_ms())
+            .take(DRAWS)
+            .collect()
+    }
+
+    #[test]
+    fn samples_stay_in_bounds() {
+        for (x, min_ms, max_ms) in [
+            (
+                Distribution::Uniform {
+                    min_ms: 100,
+                    max_ms: 200,
+                },
+                100,
+                200,
+            ),
+            (
+                Distribution::Exponential {
+                    mean_ms: 100,
+                    max_ms: 150,
+                },
+                0,
+                150,
+            ),
+            (
+                Distribution::LogNormal {
+                    median_ms: 100,
+                    sigma: 2.0,
+                    max_ms: 300,
+                },
+                0,
+                300,
+            ),
+            (
+                Distribution::TruncatedNormal {
+                    mean_ms: 100,
+                    std_dev_ms: 50,
+                    min_ms: 80,
+                    max_ms: 120,
+                },
+                80,
+                120,
+            ),
+        ] {
+            x.validate().unwrap();
+
+            for ms in draws(&x) {
+                assert!((min_ms..=max_ms).contains(&ms), "{:?} drew {}", x, ms);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_normal_far_from_the_mean_is_clamped() {
+        let x = Distribution::TruncatedNormal {
+            mean_ms: 100,
+            std_dev_ms: 1,
+            min_ms: 1_000,
+            max_ms: 2_000,
+        };
+
+        assert!(draws(&x).into_iter().all(|ms| ms == 1_000));
+    }
+
+    #[test]
+    fn exponential_has_its_mean() {
+        let x = Distribution::Exponential {
+            mean_ms: 100,
+            max_ms: u64::MAX,
+        };
+
+        let mean = draws(&x).into_iter().sum::<u64>() as f64 / DRAWS as f64;
+
+        assert!((90.0..110.0).contains(&mean), "mean was {}", mean);
+    }
+
+    #[test]
+    fn bad_distributions_are_rejected() {
+        assert!(Distribution::Uniform {
+            min_ms: 2,
+            max_ms: 1
+        }
+        .validate()
+        .is_err());
+        assert!(Distribution::Exponential {
+            mean_ms: 0,
+            max_ms: 1
+        }
+        .validate()
+        .is_err());
+        assert!(Distribution::LogNormal {
+            median_ms: 1,
+            sigma: f64::NAN,
+            max_ms: 1
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn default_timing_scales_with_the_cooldown() {
+        let timing = TimingProfile::for_cooldown(Duration::from_secs(60));
+
+        assert_eq!(timing.short.max_ms(), 6_000);
+        assert_eq!(timing.long.max_ms(), 30_000);
+    }
 }
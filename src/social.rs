@@ -0,0 +1,204 @@
+use crate::{
+    backend::{lookup_users, LOOKUP_USERS_MAX},
+    context::BotContext,
+    stats::Stats,
+    strategy::{Decision, YoinkDecision},
+    team::Account,
+    State,
+};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// What one of our accounts knows about another player.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub username: String,
+    pub follower_count: u64,
+    /// our account follows them
+    pub following: bool,
+    /// they follow our account
+    pub followed_by: bool,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl Profile {
+    pub fn is_mutual(&self) -> bool {
+        self.following && self.followed_by
+    }
+
+    /// Real people almost always pick up a few followers.
+    pub fn is_likely_bot(&self, max_followers: u64) -> bool {
+        self.follower_count <= max_followers
+    }
+}
+
+/// What each of our accounts knows about the other players. Following is different for each of our accounts.
+#[derive(Default)]
+pub struct Profiles {
+    /// keyed by (our user id, their user id)
+    profiles: RwLock<HashMap<(String, String), Profile>>,
+}
+
+impl Profiles {
+    /// `player_id` as seen by `viewer_id`. `None` until it has been looked up.
+    pub fn get(&self, viewer_id: &str, player_id: &str) -> Option<Profile> {
+        self.profiles
+            .read()
+            .expect("profiles lock poisoned")
+            .get(&(viewer_id.to_string(), player_id.to_string()))
+            .cloned()
+    }
+}
+
+/// The fid in a `farcaster:<fid>` user id. Other platforms don't have profiles.
+fn fid(user_id: &str) -> Option<u64> {
+    user_id.strip_prefix("farcaster:")?.parse().ok()
+}
+
+/// Change a strategy's decision based on who has the flag. Targets and draws are kept for the audit log.
+pub fn apply(ctx: &BotContext, account: &Account, holder_id: &str, decision: Decision) -> Decision {
+    let tunables = ctx.tunables();
+    let social = &tunables.social;
+
+    if !social.spare_mutuals && !social.snipe_bots {
+        return decision;
+    }
+
+    let Some(profile) = ctx.profiles.get(&account.user_id, holder_id) else {
+        return decision;
+    };
+
+    let (new_decision, reason) = match decision.decision {
+        YoinkDecision::Yoink | YoinkDecision::Snipe
+            if social.spare_mutuals && profile.is_mutual() =>
+        {
+            (YoinkDecision::Wait, "holder is a mutual follow")
+        }
+        YoinkDecision::Wait
+            if social.snipe_bots
+                && !profile.following
+                && profile.is_likely_bot(social.bot_max_followers) =>
+        {
+            (YoinkDecision::Snipe, "holder looks like a bot")
+        }
+        _ => return decision,
+    };
+
+    debug!(
+        holder_id,
+        username = profile.username,
+        follower_count = profile.follower_count,
+        reason,
+        "changed the decision for the holder's profile"
+    );
+
+    decision.overruled(new_decision, reason)
+}
+
+/// Look up every farcaster player in the latest stats for each of our accounts. Profiles older than `tunables.social.ttl_secs` are looked up again.
+pub async fn profiles_loop<const N: usize>(
+    mut state_rx: watch::Receiver<State<N>>,
+    cancellation_token: CancellationToken,
+    client: Client,
+    accounts: Vec<Account>,
+    ctx: Arc<BotContext>,
+) -> anyhow::Result<()> {
+    loop {
+        let stats = state_rx.borrow_and_update().stats.back().cloned();
+
+        if let Some(stats) = stats {
+            for account in accounts.iter() {
+                refresh_profiles(&cancellation_token, &client, &ctx, account, &stats).await;
+            }
+        }
+
+        tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            x = state_rx.changed() => if x.is_err() { break },
+        };
+    }
+
+    Ok(())
+}
+
+/// Look up the players that `account` has no profile for or a stale one. Failures keep the old profiles and are retried on the next state change.
+async fn refresh_profiles(
+    cancellation_token: &CancellationToken,
+    client: &Client,
+    ctx: &BotContext,
+    account: &Account,
+    stats: &Stats,
+) {
+    let profiles = &ctx.profiles;
+
+    let Some(viewer_fid) = fid(&account.user_id) else {
+        return;
+    };
+
+    let stale = {
+        let ttl = chrono::Duration::seconds(ctx.tunables().social.ttl_secs as i64);
+        let now = Utc::now();
+
+        let known = profiles.profiles.read().expect("profiles lock poisoned");
+
+        // the holder can be new enough to have no time yet
+        stats
+            .user_times
+            .keys()
+            .chain([&stats.flag.holder_id])
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter(|x| !account.team.is_teammate(x))
+            .filter(|x| {
+                known
+                    .get(&(account.user_id.clone(), x.to_string()))
+                    .map_or(true, |x| x.fetched_at + ttl <= now)
+            })
+            .filter_map(|x| fid(x))
+            .collect::<Vec<_>>()
+    };
+
+    for chunk in stale.chunks(LOOKUP_USERS_MAX) {
+        if cancellation_token.is_cancelled() {
+            return;
+        }
+
+        let users = match lookup_users(client, &account.nn_api_key, chunk, viewer_fid).await {
+            Ok(x) => x,
+            Err(err) => {
+                warn!(?err, user_id = account.user_id, "failed looking up users");
+                return;
+            }
+        };
+
+        debug!(
+            user_id = account.user_id,
+            players = users.len(),
+            "looked up profiles"
+        );
+
+        let fetched_at = Utc::now();
+
+        let mut known = profiles.profiles.write().expect("profiles lock poisoned");
+
+        for user in users {
+            known.insert(
+                (account.user_id.clone(), format!("farcaster:{}", user.fid)),
+                Profile {
+                    username: user.username,
+                    follower_count: user.follower_count,
+                    following: user.viewer_context.following,
+                    followed_by: user.viewer_context.followed_by,
+                    fetched_at,
+                },
+            );
+        }
+    }
+}
@@ -1,16 +1,35 @@
-use crate::{sleep::sleep_long_jitter, Config, State};
+use crate::{
+    clock::ServerClock,
+    context::BotContext,
+    events::GameWatcher,
+    prometheus::StatsMetrics,
+    sleep::sleep_long_jitter,
+    team::Team,
+    utils::{append_jsonl, load_jsonl},
+    State,
+};
 use anyhow::Context;
+use chrono::{DateTime, Utc};
 use im::HashMap;
+use metrics::histogram;
 use moka::future::Cache;
 use reqwest::Client;
-use serde::Deserialize;
-use std::{fmt::Debug, sync::Arc};
-use tokio::{sync::mpsc, time::Duration};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+use tokio::{sync::watch, time::Duration};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, trace, warn};
 
+/// An entry in `user_times` that adds up everyone on the platform. It is not a player.
+pub const PLATFORM_USER_ID: &str = "platform:farcaster";
+
 /// Information about the current flag holder.
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StatsFlag {
     // TODO: `yoinked_at` is a String in one place but a u64 in another. need a custom deserializer
@@ -21,7 +40,7 @@ pub struct StatsFlag {
 }
 
 /// Information about the current state of the game. Updated every 30 minutes.
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Stats {
     pub flag: StatsFlag,
@@ -29,91 +48,190 @@ pub struct Stats {
     // pub user_yoinks: HashMap<String, u64>,
     pub user_times: HashMap<String, u64>,
     pub users: HashMap<String, String>,
+    /// When we fetched `user_times` and `users`. Not part of the api.
+    #[serde(default)]
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl Stats {
+    /// Every player with time on the flag. Most time first. Ties are broken by id so that the order is stable.
+    pub fn leaderboard(&self) -> Vec<(&String, u64)> {
+        let mut leaderboard = self
+            .user_times
+            .iter()
+            .filter(|(user_id, _)| user_id.as_str() != PLATFORM_USER_ID)
+            .map(|(user_id, secs)| (user_id, *secs))
+            .collect::<Vec<_>>();
+
+        leaderboard.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+        leaderboard
+    }
 }
 
-/// Update the stats.
+/// Update the stats and publish the latest state on `state_tx`. Picks up where the state in `state_tx` left off.
 pub async fn stats_loop<const N: usize>(
-    app_state_tx: mpsc::UnboundedSender<State<N>>,
+    state_tx: watch::Sender<State<N>>,
     cancellation_token: CancellationToken,
     client: Client,
-    config: Config,
+    ctx: Arc<BotContext>,
+    team: Arc<Team>,
 ) -> anyhow::Result<()> {
-    // stats update every 30 minutes, but we don't know where in the refresh window we are. so only cache for 5 minutes
-    // TODO: Duration from config
-    let stats_cache: Cache<(), Stats> = Cache::builder()
-        .max_capacity(1)
-        .time_to_live(Duration::from_secs(5 * 60))
-        .build();
+    let mut stats_cache_ttl = ctx.tunables().stats_cache_ttl();
+    let mut stats_cache = stats_cache(stats_cache_ttl);
 
-    let mut app_state = State::<N>::default();
+    let mut stats_metrics = StatsMetrics::default();
+    let mut game_watcher = GameWatcher::default();
 
     while !cancellation_token.is_cancelled() {
-        if let Err(err) = stats_to_state(&mut app_state, &app_state_tx, &client, &stats_cache).await
-        {
-            warn!(?err, "stats_to_state failure");
-
-            // TODO: different sleep depending on the error code
-            sleep_long_jitter(&cancellation_token).await;
+        let tunables = ctx.tunables();
+
+        if stats_cache_ttl != tunables.stats_cache_ttl() {
+            // the tunables were reloaded
+            stats_cache_ttl = tunables.stats_cache_ttl();
+            stats_cache = self::stats_cache(stats_cache_ttl);
+        }
+
+        let requested_at = Instant::now();
+
+        match stats_to_state(&state_tx, &client, &ctx.clock, &stats_cache).await {
+            Ok((stats, changed)) => {
+                team.saw_holder(requested_at);
+                stats_metrics.record(&stats, &team);
+                game_watcher.observe(&stats, &team, &ctx.events);
+                ctx.holds
+                    .observe(&stats.flag.holder_id, Utc::now(), &ctx.tunables());
+
+                if changed {
+                    if let Err(err) = append_history(&ctx.data_dir, &stats) {
+                        warn!(?err, "failed saving stats history");
+                    }
+                }
+            }
+            Err(err) => {
+                warn!(?err, "stats_to_state failure");
+
+                // TODO: different sleep depending on the error code
+                sleep_long_jitter(&cancellation_token, &tunables.timing()).await;
+            }
         };
     }
 
     Ok(())
 }
 
+/// stats update every 30 minutes, but we don't know where in the refresh window we are. so only cache for a little while
+pub fn stats_cache(time_to_live: Duration) -> Cache<(), Stats> {
+    Cache::builder()
+        .max_capacity(1)
+        .time_to_live(time_to_live)
+        .build()
+}
+
+/// Publish the latest stats. Returns them and if the leaderboard changed.
 /// TODO: terrors instead of anyhow!
 pub async fn stats_to_state<const N: usize>(
-    app_state: &mut State<N>,
-    app_state_tx: &mpsc::UnboundedSender<State<N>>,
+    state_tx: &watch::Sender<State<N>>,
     client: &Client,
+    clock: &ServerClock,
     stats_cache: &Cache<(), Stats>,
-) -> anyhow::Result<()> {
-    let stats: Stats = fetch_stats(stats_cache, client).await?;
+) -> anyhow::Result<(Arc<Stats>, bool)> {
+    let stats: Stats = fetch_stats(stats_cache, client, clock).await?;
 
     let stats = Arc::new(stats);
 
-    let changed = app_state.push_stats(stats.clone());
+    let mut changed = false;
 
-    if changed {
-        let app_state = app_state.clone();
+    // modify the state in place instead of cloning it. subscribers only wake up for real changes
+    state_tx.send_if_modified(|state| {
+        state.checked_at = Some(Instant::now());
 
-        debug!(?stats.flag, "updated");
+        changed = state.push_stats(stats.clone());
+
+        // the holder changes much more often than the leaderboard
+        let holder_changed = !changed && state.push_flag(&stats);
 
-        app_state_tx.send(app_state)?;
+        changed || holder_changed
+    });
+
+    if changed {
+        debug!(?stats.flag, "updated");
     } else {
         trace!(?stats.flag, "not changed");
     }
 
-    Ok(())
+    Ok((stats, changed))
 }
 
 /// The current state of the game (with some caching). Parts of this only update every 30 minutes.
-pub async fn fetch_stats(cache: &Cache<(), Stats>, client: &Client) -> anyhow::Result<Stats> {
-    let client = client.clone();
-
+pub async fn fetch_stats(
+    cache: &Cache<(), Stats>,
+    client: &Client,
+    clock: &ServerClock,
+) -> anyhow::Result<Stats> {
     // TODO: put stats in a cache. they only refresh every 30 minutes. we set our timer to 10 minutes though just so that if things don't line up we aren't too stale
     let mut stats = cache
-        .try_get_with(
-            (),
-            client
+        .try_get_with((), async {
+            let sent = Utc::now();
+            let sent_at = Instant::now();
+
+            let response = client
                 .get("https://yoink.terminally.online/api/stats")
                 .send()
-                .await?
-                .error_for_status()?
-                .json::<Stats>(),
-        )
+                .await?;
+
+            clock.observe_response(response.headers(), sent, Utc::now());
+
+            histogram!("yoinker_request_duration_seconds", "endpoint" => "stats")
+                .record(sent_at.elapsed());
+
+            let mut stats = response.error_for_status()?.json::<Stats>().await?;
+
+            stats.fetched_at = Utc::now();
+
+            Ok::<_, reqwest::Error>(stats)
+        })
         .await
         .with_context(|| "failed fetching stats")?;
 
     // get the current flag holder
-    let flag = client
-        .get("https://yoink.terminally.online/api/flag")
-        .send()
-        .await?
-        .json::<StatsFlag>()
-        .await?;
+    let flag = fetch_flag(client, clock).await?;
 
     // override the stats' old info with the current info
     stats.flag = flag;
 
     Ok(stats)
 }
+
+/// The current flag holder. This is never cached.
+pub async fn fetch_flag(client: &Client, clock: &ServerClock) -> anyhow::Result<StatsFlag> {
+    let sent = Utc::now();
+    let sent_at = Instant::now();
+
+    let response = client
+        .get("https://yoink.terminally.online/api/flag")
+        .send()
+        .await?;
+
+    clock.observe_response(response.headers(), sent, Utc::now());
+
+    histogram!("yoinker_request_duration_seconds", "endpoint" => "flag").record(sent_at.elapsed());
+
+    let flag = response.json::<StatsFlag>().await?;
+
+    Ok(flag)
+}
+
+/// Every change to the stats is saved here (one JSON object per line) so that analytics can look back further than one run.
+pub fn history_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("stats_history.jsonl")
+}
+
+fn append_history(data_dir: &Path, stats: &Stats) -> anyhow::Result<()> {
+    append_jsonl(&history_path(data_dir), stats)
+}
+
+/// The last `limit` stats saved by [append_history]. Oldest first.
+pub fn load_history(data_dir: &Path, limit: usize) -> anyhow::Result<Vec<Stats>> {
+    Ok(load_jsonl(&history_path(data_dir), limit)?.into())
+}
@@ -1,13 +1,13 @@
 use anyhow::Context;
 use im::HashMap;
-use nanorand::Rng;
 use std::time::Duration;
 use tokio_util::sync::CancellationToken;
-use tracing::info;
 
-use crate::{sleep::sleep_with_cancel, stats::Stats, Config};
+use crate::{
+    context::BotContext, schedule::Intensity, sleep::sleep_with_cancel, stats::Stats, team::Account,
+};
 
-use super::YoinkStrategy;
+use super::{Decision, RngDraw, YoinkDecision, YoinkStrategy};
 
 /// target the first place yoinker.
 pub struct BlueShellStrategy;
@@ -16,10 +16,12 @@ impl YoinkStrategy for BlueShellStrategy {
     async fn should_yoink(
         &self,
         cancellation_token: &CancellationToken,
-        _config: &Config,
+        ctx: &BotContext,
+        _account: &Account,
         stats: &Stats,
         _user_times_diff: &HashMap<String, u64>,
-    ) -> anyhow::Result<bool> {
+        _intensity: Intensity,
+    ) -> anyhow::Result<Decision> {
         let first_place_id = stats
             .user_times
             .iter()
@@ -30,25 +32,22 @@ impl YoinkStrategy for BlueShellStrategy {
 
         let holder_id = &stats.flag.holder_id;
 
-        let mut rng = nanorand::tls_rng();
-
         if first_place_id == holder_id {
-            info!(%first_place_id, %holder_id, "fire!");
-
-            let wait_ms = rng.generate_range(0..=1_000);
-
-            sleep_with_cancel(cancellation_token, Duration::from_millis(wait_ms)).await;
-
-            Ok(true)
+            Ok(
+                Decision::new(YoinkDecision::Snipe, "holder is in first place")
+                    .with_targets([first_place_id]),
+            )
         } else {
-            info!(%first_place_id, %holder_id, "waiting to fire the shell");
-
             // TODO: look at the stats to see the next time that they are able to yoink. wait until then. if they don't yoink within some time after that, yoink anyways
-            let wait_ms = rng.generate_range(500..=2_000);
+            let wait_ms = ctx.tunables().timing().recheck.sample_ms();
 
             sleep_with_cancel(cancellation_token, Duration::from_millis(wait_ms)).await;
 
-            Ok(false)
+            Ok(
+                Decision::new(YoinkDecision::Wait, "holder is not in first place")
+                    .with_targets([first_place_id])
+                    .with_draws(vec![RngDraw::new("wait_ms", wait_ms)]),
+            )
         }
     }
 }
@@ -0,0 +1,86 @@
+use super::{Decision, RngDraw, YoinkDecision, YoinkStrategy};
+use crate::{
+    context::BotContext, schedule::Intensity, sleep::sleep_with_cancel, stats::Stats, team::Account,
+};
+use chrono::Utc;
+use im::HashMap;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+/// yoink when the hold we expect right now is worth more than waiting for a longer one.
+pub struct HoldValueStrategy;
+
+impl YoinkStrategy for HoldValueStrategy {
+    async fn should_yoink(
+        &self,
+        cancellation_token: &CancellationToken,
+        ctx: &BotContext,
+        account: &Account,
+        _stats: &Stats,
+        _user_times_diff: &HashMap<String, u64>,
+        _intensity: Intensity,
+    ) -> anyhow::Result<Decision> {
+        let now = Utc::now();
+
+        let is_teammate = |x: &str| account.team.is_teammate(x);
+
+        let tunables = ctx.tunables();
+
+        let Some(estimate) = ctx.holds.estimate(now, is_teammate, &tunables) else {
+            // nothing to go on yet. play like everyone else
+            return Ok(Decision::new(
+                YoinkDecision::Yoink,
+                "not enough holds watched",
+            ));
+        };
+
+        let cooldown_secs = tunables.cooldown_secs;
+        let step_secs = tunables.hold_value.step_secs;
+
+        // every yoink costs a cooldown. waiting pushes that cooldown (and every yoink after it) back too
+        let rate =
+            |wait_secs: u64, expected_secs: f64| expected_secs / (wait_secs + cooldown_secs) as f64;
+
+        let now_rate = rate(0, estimate.expected_secs);
+
+        // cooldowns run out while we wait, so waiting longer than one of them rarely helps
+        let best_wait = (step_secs..=cooldown_secs)
+            .step_by(step_secs as usize)
+            .filter_map(|wait_secs| {
+                let later = ctx.holds.estimate(
+                    now + chrono::Duration::seconds(wait_secs as i64),
+                    is_teammate,
+                    &tunables,
+                )?;
+
+                Some((wait_secs, later, rate(wait_secs, later.expected_secs)))
+            })
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+
+        debug!(
+            expected_secs = estimate.expected_secs,
+            available = estimate.available,
+            now_rate,
+            ?best_wait,
+            "hold value"
+        );
+
+        match best_wait {
+            Some((_, _, best_rate)) if best_rate > now_rate => {
+                let wait_ms = tunables.timing().recheck.sample_ms();
+
+                sleep_with_cancel(cancellation_token, Duration::from_millis(wait_ms)).await;
+
+                Ok(
+                    Decision::new(YoinkDecision::Wait, "a longer hold is worth waiting for")
+                        .with_draws(vec![RngDraw::new("wait_ms", wait_ms)]),
+                )
+            }
+            _ => Ok(Decision::new(
+                YoinkDecision::Yoink,
+                "the expected hold beats waiting",
+            )),
+        }
+    }
+}
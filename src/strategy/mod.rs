@@ -1,23 +1,212 @@
 mod blue_shell;
+mod hold_value;
 mod mostly_nice;
 mod red_shell;
+mod tit_for_tat;
 
-use crate::{stats::Stats, Config};
+use crate::{context::BotContext, schedule::Intensity, social, stats::Stats, team::Account};
 use im::HashMap;
+use serde::{Deserialize, Serialize};
 use tokio_util::sync::CancellationToken;
 
 pub use blue_shell::BlueShellStrategy;
+pub use hold_value::HoldValueStrategy;
 pub use mostly_nice::MostlyNiceStrategy;
 pub use red_shell::RedShellStrategy;
+pub use tit_for_tat::TitForTatStrategy;
+
+/// What a strategy wants to do about the flag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum YoinkDecision {
+    /// Leave the flag alone for now.
+    Wait,
+    /// Yoink right away.
+    Yoink,
+    /// Yoink at the exact moment that our cooldown expires. No jitter.
+    Snipe,
+}
+
+impl YoinkDecision {
+    /// A short name for logs and metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Wait => "wait",
+            Self::Yoink => "yoink",
+            Self::Snipe => "snipe",
+        }
+    }
+}
+
+/// What a strategy decided and why. Everything random that went into it is kept for the audit log.
+#[derive(Clone, Debug, Serialize)]
+pub struct Decision {
+    pub decision: YoinkDecision,
+    /// Short and the same every time so that it can be grouped on.
+    pub reason: &'static str,
+    /// The players that the strategy was after.
+    pub targets: Vec<String>,
+    pub rng: Vec<RngDraw>,
+}
+
+/// A random number that a strategy rolled.
+#[derive(Clone, Debug, Serialize)]
+pub struct RngDraw {
+    pub name: &'static str,
+    pub value: u64,
+}
+
+impl Decision {
+    pub fn new(decision: YoinkDecision, reason: &'static str) -> Self {
+        Self {
+            decision,
+            reason,
+            targets: vec![],
+            rng: vec![],
+        }
+    }
+
+    pub fn with_targets<T: ToString>(mut self, targets: impl IntoIterator<Item = T>) -> Self {
+        self.targets = targets.into_iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    pub fn with_draws(mut self, rng: Vec<RngDraw>) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Swap in a different decision and reason. The targets and draws are kept for the audit log.
+    pub fn overruled(self, decision: YoinkDecision, reason: &'static str) -> Self {
+        Self {
+            decision,
+            reason,
+            ..self
+        }
+    }
+}
+
+impl RngDraw {
+    pub fn new(name: &'static str, value: u64) -> Self {
+        Self { name, value }
+    }
+}
 
 /// A strategy for playing the yoink game.
 pub trait YoinkStrategy {
-    /// Determine if we should yoink the flag.
+    /// Determine if we should yoink the flag. `intensity` is how hard the schedule says to play right now.
+    async fn should_yoink(
+        &self,
+        cancellation_token: &CancellationToken,
+        ctx: &BotContext,
+        account: &Account,
+        stats: &Stats,
+        user_times_diff: &HashMap<String, u64>,
+        intensity: Intensity,
+    ) -> anyhow::Result<Decision>;
+}
+
+/// Which strategy the bot plays. Chosen by `tunables.strategy` and swappable at runtime.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyKind {
+    #[default]
+    RedShell,
+    BlueShell,
+    MostlyNice,
+    TitForTat,
+    HoldValue,
+}
+
+impl StrategyKind {
+    /// The name used in the config.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RedShell => "red_shell",
+            Self::BlueShell => "blue_shell",
+            Self::MostlyNice => "mostly_nice",
+            Self::TitForTat => "tit_for_tat",
+            Self::HoldValue => "hold_value",
+        }
+    }
+}
+
+impl YoinkStrategy for StrategyKind {
+    #[tracing::instrument(skip_all, fields(strategy = self.as_str()))]
     async fn should_yoink(
         &self,
         cancellation_token: &CancellationToken,
-        config: &Config,
+        ctx: &BotContext,
+        account: &Account,
         stats: &Stats,
         user_times_diff: &HashMap<String, u64>,
-    ) -> anyhow::Result<bool>;
+        intensity: Intensity,
+    ) -> anyhow::Result<Decision> {
+        let decision = match self {
+            Self::RedShell => {
+                RedShellStrategy
+                    .should_yoink(
+                        cancellation_token,
+                        ctx,
+                        account,
+                        stats,
+                        user_times_diff,
+                        intensity,
+                    )
+                    .await
+            }
+            Self::BlueShell => {
+                BlueShellStrategy
+                    .should_yoink(
+                        cancellation_token,
+                        ctx,
+                        account,
+                        stats,
+                        user_times_diff,
+                        intensity,
+                    )
+                    .await
+            }
+            Self::MostlyNice => {
+                MostlyNiceStrategy
+                    .should_yoink(
+                        cancellation_token,
+                        ctx,
+                        account,
+                        stats,
+                        user_times_diff,
+                        intensity,
+                    )
+                    .await
+            }
+            Self::TitForTat => {
+                TitForTatStrategy
+                    .should_yoink(
+                        cancellation_token,
+                        ctx,
+                        account,
+                        stats,
+                        user_times_diff,
+                        intensity,
+                    )
+                    .await
+            }
+            Self::HoldValue => {
+                HoldValueStrategy
+                    .should_yoink(
+                        cancellation_token,
+                        ctx,
+                        account,
+                        stats,
+                        user_times_diff,
+                        intensity,
+                    )
+                    .await
+            }
+        }?;
+
+        let decision = social::apply(ctx, account, &stats.flag.holder_id, decision);
+
+        Ok(intensity.apply(decision))
+    }
 }
@@ -1,10 +1,12 @@
-use super::YoinkStrategy;
-use crate::{sleep::sleep_with_cancel, stats::Stats, Config};
+use super::{Decision, RngDraw, YoinkDecision, YoinkStrategy};
+use crate::{
+    context::BotContext, schedule::Intensity, sleep::sleep_with_cancel, stats::Stats, team::Account,
+};
 use im::HashMap;
 use nanorand::Rng;
 use std::time::Duration;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, info};
+use tracing::debug;
 
 /// Prefer to target players that are above 6 hours on the leaderboard.
 pub struct MostlyNiceStrategy;
@@ -13,15 +15,23 @@ impl YoinkStrategy for MostlyNiceStrategy {
     async fn should_yoink(
         &self,
         cancellation_token: &CancellationToken,
-        config: &Config,
+        ctx: &BotContext,
+        account: &Account,
         stats: &Stats,
         _user_times_diff: &HashMap<String, u64>,
-    ) -> anyhow::Result<bool> {
+        intensity: Intensity,
+    ) -> anyhow::Result<Decision> {
         // TODO: if we don't have the flag, but the person who has the flag has a lower score than us, leave them alone. we don't want to be jerks
         // TODO: move this to a "should_not_yoink" function
 
         let mut rng = nanorand::tls_rng();
 
+        let mut draws = vec![];
+
+        let tunables = ctx.tunables();
+        let mostly_nice = &tunables.mostly_nice;
+        let timing = tunables.timing();
+
         // TODO: stats only update every 30 minutes!
         let holder_time = stats
             .user_times
@@ -30,40 +40,49 @@ impl YoinkStrategy for MostlyNiceStrategy {
             .unwrap_or(0);
 
         // TODO: stats only update every 30 minutes!
-        let my_time = stats.user_times.get(&config.user_id).copied().unwrap_or(0);
+        let my_time = stats.user_times.get(&account.user_id).copied().unwrap_or(0);
 
         // let jerk_threshold = my_time.saturating_sub(30 * 60);
-        let jerk_threshold = 6 * 3600;
-        let nice_chance: u16 = 75; // TODO: dynamic nice_chance based on their time
+        let jerk_threshold = mostly_nice.jerk_threshold_secs;
+        let nice_chance = mostly_nice.nice_chance; // TODO: dynamic nice_chance based on their time
 
-        if holder_time < jerk_threshold {
+        // aggressive hours are no time to be nice
+        if holder_time < jerk_threshold && intensity != Intensity::Aggressive {
             // the current flag holder has a lower score than us. don't be a jerk
             if holder_time < my_time {
                 let x = rng.generate_range(0..100);
+                draws.push(RngDraw::new("nice_roll", x as u64));
+
                 if x <= nice_chance {
-                    debug!(
-                        holder_id = stats.flag.holder_id.as_str(),
-                        "flag holder has too low of a score. not yoinking"
-                    );
+                    let x = timing.recheck.sample_ms();
+                    draws.push(RngDraw::new("skip_ms", x));
 
-                    let x = rng.generate_range(1_000..10_000);
                     sleep_with_cancel(cancellation_token, Duration::from_millis(x)).await;
 
-                    return Ok(false);
+                    return Ok(Decision::new(YoinkDecision::Wait, "holder has a low score")
+                        .with_targets([&stats.flag.holder_id])
+                        .with_draws(draws));
                 }
-
-                info!("being a jerk");
             }
         }
 
         // we do NOT have the flag. try to yoink it
-        let wait_ms = rng.generate_range(0..=3_000);
+        let wait_ms = timing.pre_yoink.sample_ms();
+        draws.push(RngDraw::new("wait_ms", wait_ms));
 
-        info!(my_time, ?stats.flag, wait_ms, "preparing to yoink the flag");
+        debug!(my_time, holder_time, wait_ms, "preparing to yoink the flag");
 
         // TODO: is this sleep a good idea? it wastes some of our cooldown timer, but i feel like giving other bots some time to play is a good idea
         sleep_with_cancel(cancellation_token, Duration::from_millis(wait_ms)).await;
 
-        Ok(true)
+        let reason = if holder_time < jerk_threshold && holder_time < my_time {
+            "being a jerk"
+        } else {
+            "holder has a high score"
+        };
+
+        Ok(Decision::new(YoinkDecision::Yoink, reason)
+            .with_targets([&stats.flag.holder_id])
+            .with_draws(draws))
     }
 }
@@ -1,13 +1,11 @@
-use super::YoinkStrategy;
+use super::{Decision, RngDraw, YoinkDecision, YoinkStrategy};
 use crate::{
-    sleep::{sleep_short_jitter, sleep_with_cancel},
-    stats::Stats,
-    Config,
+    context::BotContext, schedule::Intensity, sleep::sleep_with_cancel, stats::Stats, team::Account,
 };
 use im::HashMap;
 use std::{cmp::Reverse, time::Duration};
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, info};
+use tracing::debug;
 
 /// target the recent top 3 yoinkers.
 pub struct RedShellStrategy;
@@ -16,14 +14,17 @@ impl YoinkStrategy for RedShellStrategy {
     async fn should_yoink(
         &self,
         cancellation_token: &CancellationToken,
-        config: &Config,
+        ctx: &BotContext,
+        account: &Account,
         stats: &Stats,
         user_times_diff: &HashMap<String, u64>,
-    ) -> anyhow::Result<bool> {
+        _intensity: Intensity,
+    ) -> anyhow::Result<Decision> {
         let mut targets = user_times_diff
             .iter()
             .filter(|(id, _)| {
-                id.as_str() != "platform:farcaster" && id.as_str() != config.user_id.as_str()
+                // never target ourselves or our teammates
+                id.as_str() != "platform:farcaster" && !account.team.is_teammate(id.as_str())
             })
             .collect::<Vec<_>>();
 
@@ -69,39 +70,34 @@ impl YoinkStrategy for RedShellStrategy {
         let holder_time = stats.user_times.get(holder_id).copied().unwrap_or(0);
         let holder_diff = user_times_diff.get(holder_id).copied().unwrap_or(0);
 
-        let our_time = stats.user_times.get(&config.user_id).copied().unwrap_or(0);
-        let our_diff = user_times_diff.get(&config.user_id).copied().unwrap_or(0);
+        let our_time = stats.user_times.get(&account.user_id).copied().unwrap_or(0);
+        let our_diff = user_times_diff.get(&account.user_id).copied().unwrap_or(0);
 
-        if targets.iter().any(|t| t.id == holder_id) {
-            info!(
-                holder_id,
-                holder_diff,
-                holder_time,
-                our_diff,
-                our_time,
-                ?targets,
-                "fire!"
-            );
+        debug!(
+            holder_id,
+            holder_diff,
+            holder_time,
+            our_diff,
+            our_time,
+            ?targets,
+            "red shell"
+        );
 
-            sleep_short_jitter(cancellation_token).await;
+        let target_ids = targets.iter().map(|x| x.id);
 
-            Ok(true)
+        if targets.iter().any(|t| t.id == holder_id) {
+            // every second that they hold the flag counts. don't wait around
+            Ok(Decision::new(YoinkDecision::Snipe, "holder is a target").with_targets(target_ids))
         } else {
-            debug!(
-                holder_id,
-                holder_diff,
-                holder_time,
-                our_diff,
-                our_time,
-                ?targets,
-                "waiting to fire the shell"
-            );
-
             // TODO: look at the stats to see the next time that they are able to yoink. wait until then. if they don't yoink within some time after that, yoink anyways
 
-            sleep_with_cancel(cancellation_token, Duration::from_secs(1)).await;
+            let wait_ms = ctx.tunables().timing().recheck.sample_ms();
+
+            sleep_with_cancel(cancellation_token, Duration::from_millis(wait_ms)).await;
 
-            Ok(false)
+            Ok(Decision::new(YoinkDecision::Wait, "holder is not a target")
+                .with_targets(target_ids)
+                .with_draws(vec![RngDraw::new("wait_ms", wait_ms)]))
         }
     }
 }
@@ -0,0 +1,52 @@
+use super::{Decision, RngDraw, YoinkDecision, YoinkStrategy};
+use crate::{
+    context::BotContext, schedule::Intensity, sleep::sleep_with_cancel, stats::Stats, team::Account,
+};
+use im::HashMap;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+/// leave players alone until they take the flag from us. then hit back until the grudge fades.
+pub struct TitForTatStrategy;
+
+impl YoinkStrategy for TitForTatStrategy {
+    async fn should_yoink(
+        &self,
+        cancellation_token: &CancellationToken,
+        ctx: &BotContext,
+        account: &Account,
+        stats: &Stats,
+        _user_times_diff: &HashMap<String, u64>,
+        _intensity: Intensity,
+    ) -> anyhow::Result<Decision> {
+        let holder_id = &stats.flag.holder_id;
+
+        let tunables = ctx.tunables();
+
+        let retaliate_score = tunables.tit_for_tat.retaliate_score;
+
+        // teammates never have a reputation, but don't count on that
+        let score = if account.team.is_teammate(holder_id) {
+            0.0
+        } else {
+            ctx.reputation.score(holder_id, &tunables.reputation)
+        };
+
+        debug!(holder_id, score, retaliate_score, "tit for tat");
+
+        if score >= retaliate_score {
+            Ok(Decision::new(YoinkDecision::Snipe, "retaliating")
+                .with_targets([holder_id.as_str()]))
+        } else {
+            let wait_ms = tunables.timing().recheck.sample_ms();
+
+            sleep_with_cancel(cancellation_token, Duration::from_millis(wait_ms)).await;
+
+            Ok(
+                Decision::new(YoinkDecision::Wait, "holder has been cooperative")
+                    .with_draws(vec![RngDraw::new("wait_ms", wait_ms)]),
+            )
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{admin::Control, sleep::sleep_with_cancel};
+use metrics::counter;
+use serde::Serialize;
+use std::{fmt, future::Future, sync::Arc, time::Duration};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    task::JoinHandle,
+    time::Instant,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// Restart a crashed task this many times in a row before giving up.
+const SUPERVISOR_MAX_RESTARTS: u32 = 5;
+
+/// A task that ran this long before crashing was healthy. Its restart count starts over.
+const SUPERVISOR_HEALTHY_AFTER: Duration = Duration::from_secs(5 * 60);
+
+const SUPERVISOR_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const SUPERVISOR_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// How a supervised task is doing. Shown by the admin api.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TaskHealth {
+    Running {
+        restarts: u32,
+    },
+    /// crashed and waiting to start again
+    Restarting {
+        restarts: u32,
+        error: String,
+    },
+    /// finished on its own or shut down
+    Stopped,
+    /// crashed too many times. the bot is shutting down
+    Failed {
+        error: String,
+    },
+}
+
+impl TaskHealth {
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, Self::Running { .. } | Self::Stopped)
+    }
+}
+
+/// A supervised task crashed too many times. `main` gives this its own exit code.
+#[derive(Debug)]
+pub struct TaskFailed {
+    pub task: &'static str,
+}
+
+impl fmt::Display for TaskFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the {} task failed too many times", self.task)
+    }
+}
+
+/// Runs background tasks. Crashed tasks are restarted with backoff. If one keeps crashing, everything is cancelled.
+pub struct Supervisor {
+    cancellation_token: CancellationToken,
+    control: Arc<Control>,
+    handles: Vec<JoinHandle<anyhow::Result<()>>>,
+}
+
+impl Supervisor {
+    pub fn new(cancellation_token: CancellationToken, control: Arc<Control>) -> Self {
+        Self {
+            cancellation_token,
+            control,
+            handles: vec![],
+        }
+    }
+
+    /// Run a task in the background. `task` is called again for every restart.
+    pub fn spawn<F, Fut>(&mut self, name: &'static str, task: F)
+    where
+        F: FnMut(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        self.handles.push(tokio::spawn(supervise(
+            name,
+            self.cancellation_token.clone(),
+            self.control.clone(),
+            task,
+        )));
+    }
+
+    /// Wait for every task to stop. This errors if any of them failed for good.
+    pub async fn join(self) -> anyhow::Result<()> {
+        let mut exit = Ok(());
+
+        for handle in self.handles {
+            let result = handle
+                .await
+                .expect("supervise catches panics, so it shouldn't ever have a join error");
+
+            // keep the first failure. it probably caused the rest
+            if exit.is_ok() {
+                exit = result;
+            }
+        }
+
+        exit
+    }
+}
+
+async fn supervise<F, Fut>(
+    name: &'static str,
+    cancellation_token: CancellationToken,
+    control: Arc<Control>,
+    mut task: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(CancellationToken) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let mut restarts = 0;
+    let mut backoff = SUPERVISOR_BACKOFF_MIN;
+
+    loop {
+        control.set_task_health(name, TaskHealth::Running { restarts });
+
+        let started = Instant::now();
+
+        // a separate task so that panics are caught too
+        let result = match tokio::spawn(task(cancellation_token.clone())).await {
+            Ok(x) => x,
+            Err(err) => Err(anyhow::anyhow!("panicked: {}", err)),
+        };
+
+        let err = match result {
+            Ok(()) => {
+                control.set_task_health(name, TaskHealth::Stopped);
+                return Ok(());
+            }
+            Err(err) if cancellation_token.is_cancelled() => {
+                // restarting would be pointless
+                error!(task = name, ?err, "task failed while shutting down");
+                control.set_task_health(name, TaskHealth::Stopped);
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+
+        if started.elapsed() >= SUPERVISOR_HEALTHY_AFTER {
+            restarts = 0;
+            backoff = SUPERVISOR_BACKOFF_MIN;
+        }
+
+        if restarts >= SUPERVISOR_MAX_RESTARTS {
+            error!(
+                task = name,
+                restarts,
+                ?err,
+                "task keeps failing. shutting down"
+            );
+
+            control.set_task_health(
+                name,
+                TaskHealth::Failed {
+                    error: format!("{:#}", err),
+                },
+            );
+
+            cancellation_token.cancel();
+
+            return Err(err.context(TaskFailed { task: name }));
+        }
+
+        restarts += 1;
+
+        counter!("yoinker_task_restarts_total", "task" => name).increment(1);
+
+        warn!(
+            task = name,
+            restarts,
+            ?backoff,
+            ?err,
+            "task failed. restarting"
+        );
+
+        control.set_task_health(
+            name,
+            TaskHealth::Restarting {
+                restarts,
+                error: format!("{:#}", err),
+            },
+        );
+
+        sleep_with_cancel(&cancellation_token, backoff).await;
+
+        if cancellation_token.is_cancelled() {
+            control.set_task_health(name, TaskHealth::Stopped);
+            return Ok(());
+        }
+
+        backoff = (backoff * 2).min(SUPERVISOR_BACKOFF_MAX);
+    }
+}
+
+/// Cancel on SIGINT or SIGTERM so that everything shuts down gracefully. A second signal exits immediately.
+pub async fn cancel_on_signal(cancellation_token: CancellationToken) -> anyhow::Result<()> {
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+
+    let name = tokio::select! {
+        _ = sigint.recv() => "SIGINT",
+        _ = sigterm.recv() => "SIGTERM",
+    };
+
+    info!("[{}] received. shutting down", name);
+
+    cancellation_token.cancel();
+
+    let (name, kind) = tokio::select! {
+        _ = sigint.recv() => ("SIGINT", SignalKind::interrupt()),
+        _ = sigterm.recv() => ("SIGTERM", SignalKind::terminate()),
+    };
+
+    warn!(
+        "second signal ([{}]) received. exiting without waiting",
+        name
+    );
+
+    // what a shell reports for a process killed by the signal
+    std::process::exit(128 + kind.as_raw_value());
+}
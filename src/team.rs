@@ -0,0 +1,312 @@
+use crate::{
+    backend::{lookup_signer, BackendKind},
+    secret::Secret,
+    Config,
+};
+use anyhow::Context;
+use reqwest::Client;
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{debug, info};
+
+/// After a teammate yoinks, give the flag endpoint this long to show the new holder before anyone else on the team tries.
+pub const TEAM_YOINK_GRACE: Duration = Duration::from_secs(5);
+
+/// One Farcaster account that we play with.
+#[derive(Clone)]
+pub struct Account {
+    pub user_id: String,
+    pub nn_api_key: Secret<String>,
+    pub nn_signer_uuid: Secret<String>,
+    pub team: Arc<Team>,
+}
+
+/// All of the accounts run by this process. Used so that our accounts never yoink from each other.
+pub struct Team {
+    user_ids: HashSet<String>,
+    /// The user_id and time of the most recent yoink by anyone on the team.
+    last_yoink: Mutex<Option<(String, Instant)>>,
+    /// The account that took the flag since the stats last showed who has it and when. Yoinking blind would take it back from them.
+    blind_holder: Mutex<Option<(String, Instant)>>,
+}
+
+impl Team {
+    /// Build the team from the account lists in the config.
+    ///
+    /// With the neynar backend, user ids are looked up from the signers. If user ids are also configured, they must match what Neynar says.
+    /// The other backends never talk to Neynar. They take the user ids from the config.
+    pub async fn accounts_from_config(
+        config: &Config,
+        client: &Client,
+    ) -> anyhow::Result<Vec<Account>> {
+        let user_ids = match config.backend {
+            BackendKind::Neynar => user_ids_from_signers(config, client).await?,
+            BackendKind::DryRun | BackendKind::Fake => {
+                if config.user_id.is_empty() {
+                    anyhow::bail!("at least one user_id is required without the neynar backend");
+                }
+
+                config.user_id.clone()
+            }
+        };
+
+        let unique_user_ids = user_ids.iter().cloned().collect::<HashSet<_>>();
+
+        if unique_user_ids.len() != user_ids.len() {
+            anyhow::bail!("multiple accounts are for the same user. {:?}", user_ids);
+        }
+
+        let team = Arc::new(Self {
+            user_ids: unique_user_ids,
+            last_yoink: Mutex::new(None),
+            blind_holder: Mutex::new(None),
+        });
+
+        let accounts = user_ids
+            .into_iter()
+            .enumerate()
+            .map(|(i, user_id)| Account {
+                user_id,
+                // one shared key or one per signer. empty if the backend doesn't need them
+                nn_api_key: config
+                    .nn_api_key
+                    .get(i)
+                    .or(config.nn_api_key.first())
+                    .cloned()
+                    .unwrap_or_default(),
+                nn_signer_uuid: config.nn_signer_uuid.get(i).cloned().unwrap_or_default(),
+                team: team.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(accounts)
+    }
+
+    /// Is this user one of our accounts?
+    pub fn is_teammate(&self, user_id: &str) -> bool {
+        self.user_ids.contains(user_id)
+    }
+
+    /// Claim the right to yoink right now.
+    ///
+    /// Returns false if a teammate yoinked within [TEAM_YOINK_GRACE]. Two of our accounts yoinking at the same moment just wastes a cooldown.
+    pub fn try_claim_yoink(&self, user_id: &str) -> bool {
+        let mut last_yoink = self.last_yoink.lock().expect("team lock poisoned");
+
+        let now = Instant::now();
+
+        if let Some((teammate_id, at)) = last_yoink.as_ref() {
+            if teammate_id != user_id && now.duration_since(*at) < TEAM_YOINK_GRACE {
+                debug!(%teammate_id, "a teammate just yoinked");
+                return false;
+            }
+        }
+
+        *last_yoink = Some((user_id.to_string(), now));
+
+        true
+    }
+
+    /// Remember that `user_id` took the flag. Blind yoinks wait until the stats show who has it.
+    pub fn record_yoinked(&self, user_id: &str) {
+        *self.blind_holder.lock().expect("team lock poisoned") =
+            Some((user_id.to_string(), Instant::now()));
+    }
+
+    /// Stats that were requested at `requested_at` arrived. They show who has the flag if they were requested after our last yoink.
+    pub fn saw_holder(&self, requested_at: Instant) {
+        let mut blind_holder = self.blind_holder.lock().expect("team lock poisoned");
+
+        if blind_holder
+            .as_ref()
+            .is_some_and(|(_, at)| *at <= requested_at)
+        {
+            *blind_holder = None;
+        }
+    }
+
+    /// The account that took the flag since the stats last showed who has it. Unlike a claim, this doesn't run out.
+    pub fn blind_holder(&self) -> Option<String> {
+        self.blind_holder
+            .lock()
+            .expect("team lock poisoned")
+            .as_ref()
+            .map(|(user_id, _)| user_id.clone())
+    }
+
+    /// Forget the last claim as if [TEAM_YOINK_GRACE] had passed.
+    #[cfg(test)]
+    pub fn expire_claim(&self) {
+        *self.last_yoink.lock().expect("team lock poisoned") = None;
+    }
+
+    /// Give up a claim from [Team::try_claim_yoink] because the yoink didn't take the flag.
+    pub fn release_yoink(&self, user_id: &str) {
+        let mut last_yoink = self.last_yoink.lock().expect("team lock poisoned");
+
+        if last_yoink.as_ref().is_some_and(|(x, _)| x == user_id) {
+            *last_yoink = None;
+        }
+    }
+}
+
+/// Look up the user id of every signer. A single api key can be shared by all of them.
+async fn user_ids_from_signers(config: &Config, client: &Client) -> anyhow::Result<Vec<String>> {
+    let num_accounts = config.nn_signer_uuid.len();
+
+    if num_accounts == 0 {
+        anyhow::bail!("at least one nn_signer_uuid is required");
+    }
+
+    if !config.user_id.is_empty() && config.user_id.len() != num_accounts {
+        anyhow::bail!(
+            "{} user ids but {} signers. there must be one user_id per nn_signer_uuid (or none at all)",
+            config.user_id.len(),
+            num_accounts,
+        );
+    }
+
+    if config.nn_api_key.len() != 1 && config.nn_api_key.len() != num_accounts {
+        anyhow::bail!(
+            "{} signers but {} api keys. there must be one nn_api_key or one per nn_signer_uuid",
+            num_accounts,
+            config.nn_api_key.len()
+        );
+    }
+
+    let mut user_ids = Vec::with_capacity(num_accounts);
+
+    for (i, nn_signer_uuid) in config.nn_signer_uuid.iter().enumerate() {
+        let nn_api_key = config.nn_api_key.get(i).unwrap_or(&config.nn_api_key[0]);
+
+        let fid = lookup_signer(client, nn_api_key, nn_signer_uuid)
+            .await
+            .with_context(|| format!("looking up signer #{}", i))?
+            .fid
+            .with_context(|| format!("signer #{} has no fid. is it approved?", i))?;
+
+        let user_id = format!("farcaster:{}", fid);
+
+        if let Some(configured) = config.user_id.get(i) {
+            if *configured != user_id {
+                anyhow::bail!(
+                    "user_id {} does not match signer #{}. neynar says it belongs to {}",
+                    configured,
+                    i,
+                    user_id
+                );
+            }
+        }
+
+        info!(%user_id, "found user_id for signer #{}", i);
+
+        user_ids.push(user_id);
+    }
+
+    Ok(user_ids)
+}
+
+/// One account per user id, all on the same team. Nothing is looked up.
+#[cfg(test)]
+pub fn test_accounts(user_ids: &[&str]) -> Vec<Account> {
+    let team = Arc::new(Team {
+        user_ids: user_ids.iter().map(|x| x.to_string()).collect(),
+        last_yoink: Mutex::new(None),
+        blind_holder: Mutex::new(None),
+    });
+
+    user_ids
+        .iter()
+        .map(|x| Account {
+            user_id: x.to_string(),
+            nn_api_key: Secret::new("test".to_string()),
+            nn_signer_uuid: Secret::new(format!("{}-signer", x)),
+            team: team.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_teammate_yoinks_at_a_time() {
+        let accounts = test_accounts(&["farcaster:1", "farcaster:2"]);
+        let team = &accounts[0].team;
+
+        assert!(team.is_teammate("farcaster:2"));
+        assert!(!team.is_teammate("farcaster:3"));
+
+        assert!(team.try_claim_yoink("farcaster:1"));
+        assert!(!team.try_claim_yoink("farcaster:2"));
+
+        // the same account can try again
+        assert!(team.try_claim_yoink("farcaster:1"));
+    }
+
+    #[test]
+    fn releasing_lets_a_teammate_yoink() {
+        let accounts = test_accounts(&["farcaster:1", "farcaster:2"]);
+        let team = &accounts[0].team;
+
+        assert!(team.try_claim_yoink("farcaster:1"));
+
+        // only the account holding the claim can release it
+        team.release_yoink("farcaster:2");
+        assert!(!team.try_claim_yoink("farcaster:2"));
+
+        team.release_yoink("farcaster:1");
+        assert!(team.try_claim_yoink("farcaster:2"));
+    }
+
+    #[tokio::test]
+    async fn other_backends_take_user_ids_from_the_config() {
+        let config: Config = toml::from_str(
+            "cast_hash = \"0x1\"\nbackend = \"fake\"\nuser_id = [\"farcaster:1\", \"farcaster:2\"]",
+        )
+        .unwrap();
+
+        // no signers to look up. nothing is sent
+        let accounts = Team::accounts_from_config(&config, &Client::new())
+            .await
+            .unwrap();
+
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[1].user_id, "farcaster:2");
+        assert!(accounts[0].team.is_teammate("farcaster:2"));
+
+        let config: Config = toml::from_str("cast_hash = \"0x1\"\nbackend = \"fake\"").unwrap();
+
+        assert!(Team::accounts_from_config(&config, &Client::new())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn blind_holders_last_until_the_stats_show_the_holder() {
+        let accounts = test_accounts(&["farcaster:1", "farcaster:2"]);
+        let team = &accounts[0].team;
+
+        assert_eq!(team.blind_holder(), None);
+
+        let requested_at = Instant::now();
+
+        team.record_yoinked("farcaster:1");
+        team.expire_claim();
+
+        assert_eq!(team.blind_holder().as_deref(), Some("farcaster:1"));
+
+        // these stats were on their way before the yoink
+        team.saw_holder(requested_at);
+
+        assert_eq!(team.blind_holder().as_deref(), Some("farcaster:1"));
+
+        team.saw_holder(Instant::now());
+
+        assert_eq!(team.blind_holder(), None);
+    }
+}
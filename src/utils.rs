@@ -2,16 +2,23 @@ use crate::APP_USER_AGENT;
 use anyhow::Context;
 use im::HashMap;
 use reqwest::Client;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::hash::Hash;
-use std::{env, ops::SubAssign, time::Duration};
+use std::io::{BufRead, BufReader, Write};
+use std::{env, fs::OpenOptions, path::Path, sync::Mutex, time::Duration};
 use tracing::warn;
-use tracing_subscriber::{EnvFilter, FmtSubscriber};
+use tracing_subscriber::{
+    fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+};
 
 /// create a new HTTPS-only client with our app's user agent.
 pub async fn https_client() -> anyhow::Result<Client> {
     let client = Client::builder()
         .connect_timeout(Duration::from_secs(5))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_nodelay(true)
         .http2_keep_alive_interval(Duration::from_secs(50))
         .http2_keep_alive_timeout(Duration::from_secs(60))
         .https_only(true)
@@ -22,47 +29,114 @@ pub async fn https_client() -> anyhow::Result<Client> {
     Ok(client)
 }
 
+/// Keeps optional tracing exporters alive. Drop it at exit to flush them.
+#[derive(Default)]
+pub struct LoggingGuard {
+    #[cfg(feature = "otlp")]
+    tracer_provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(tracer_provider) = self.tracer_provider.take() {
+            if let Err(err) = tracer_provider.shutdown() {
+                eprintln!("failed flushing traces: {:?}", err);
+            }
+        }
+    }
+}
+
 /// Log formatting is handled outside of the Config.
 /// Choose by setting the LOG_FORMAT environment variable.
 /// "pretty" logging for development or "json" logging for log collectors.
-pub fn init_logging() -> anyhow::Result<()> {
-    let log_format = env::var("LOG_FORMAT").ok();
+/// Logs go to stderr so that stdout is left for command output. Or to `log_file` if one is given.
+///
+/// The `tokio-console` feature serves tokio-console and the `otlp` feature exports spans to an OTLP collector.
+pub fn init_logging(log_file: Option<&Path>) -> anyhow::Result<LoggingGuard> {
+    #[allow(unused_mut)]
+    let mut guard = LoggingGuard::default();
 
-    let subscriber = FmtSubscriber::builder().with_env_filter(EnvFilter::from_default_env());
+    let log_format = env::var("LOG_FORMAT").ok();
 
-    // TODO: tokio-console
-    match log_format.as_deref() {
-        Some("json") => {
-            let subscriber = subscriber.json().finish();
+    let fmt_layer = match log_file {
+        Some(log_file) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(log_file)
+                .with_context(|| format!("opening {}", log_file.display()))?;
 
-            tracing::subscriber::set_global_default(subscriber)
-                .context("Setting default subscriber failed")?;
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(BoxMakeWriter::new(Mutex::new(file)))
         }
-        Some("pretty") | None => {
-            let subscriber = subscriber.pretty().finish();
+        None => tracing_subscriber::fmt::layer().with_writer(BoxMakeWriter::new(std::io::stderr)),
+    };
 
-            tracing::subscriber::set_global_default(subscriber)
-                .context("Setting default subscriber failed")?;
-        }
+    let fmt_layer = match log_format.as_deref() {
+        Some("json") => fmt_layer.json().boxed(),
+        Some("pretty") | None => fmt_layer.pretty().boxed(),
         Some(x) => {
             anyhow::bail!("Invalid log format: {}", x);
         }
+    };
+
+    #[allow(unused_mut)]
+    let mut layers = vec![fmt_layer.with_filter(EnvFilter::from_default_env()).boxed()];
+
+    // tokio-console does its own filtering
+    #[cfg(feature = "tokio-console")]
+    layers.push(console_subscriber::spawn().boxed());
+
+    #[cfg(feature = "otlp")]
+    {
+        use opentelemetry::trace::TracerProvider as _;
+
+        // the endpoint and headers come from the standard OTEL_EXPORTER_OTLP_* environment variables
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .build()
+            .context("building the otlp exporter")?;
+
+        let tracer_provider = opentelemetry_sdk::trace::TracerProvider::builder()
+            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_resource(opentelemetry_sdk::Resource::new([
+                opentelemetry::KeyValue::new("service.name", env!("CARGO_PKG_NAME")),
+            ]))
+            .build();
+
+        let tracer = tracer_provider.tracer(env!("CARGO_PKG_NAME"));
+
+        layers.push(
+            tracing_opentelemetry::layer()
+                .with_tracer(tracer)
+                .with_filter(EnvFilter::from_default_env())
+                .boxed(),
+        );
+
+        guard.tracer_provider = Some(tracer_provider);
     }
 
-    Ok(())
+    tracing_subscriber::registry()
+        .with(layers)
+        .try_init()
+        .context("Setting default subscriber failed")?;
+
+    Ok(guard)
 }
 
 /// helper function to subtract two hashmaps and return the diference.
-pub fn subtract_hashmaps<K, V>(newer: &HashMap<K, V>, older: &HashMap<K, V>) -> HashMap<K, V>
+/// Values that went down (a reset) count as 0 instead of wrapping.
+pub fn subtract_hashmaps<K>(newer: &HashMap<K, u64>, older: &HashMap<K, u64>) -> HashMap<K, u64>
 where
     K: Clone + Debug + Hash + PartialEq + Eq,
-    V: Copy + Clone + SubAssign + PartialOrd<u64>,
 {
     let mut result = newer.clone();
 
     for (key, older_value) in older {
         if let Some(new_value) = result.get_mut(key) {
-            *new_value -= *older_value;
+            *new_value = new_value.saturating_sub(*older_value);
         } else {
             warn!(?key, "missing key!")
         }
@@ -72,3 +146,66 @@ where
 
     result
 }
+
+/// format a duration for humans. "1h 2m 3s"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    match (hours, minutes) {
+        (0, 0) => format!("{}s", secs),
+        (0, _) => format!("{}m {}s", minutes, secs),
+        _ => format!("{}h {}m {}s", hours, minutes, secs),
+    }
+}
+
+/// Add one JSON object as a line at the end of `path`. The file and its directory are created if needed.
+pub fn append_jsonl<T: Serialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating {}", parent.display()))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))?;
+
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+
+    file.write_all(&line)
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+/// The last `limit` lines saved by [append_jsonl]. Oldest first. Empty if the file doesn't exist.
+pub fn load_jsonl<T: DeserializeOwned>(path: &Path, limit: usize) -> anyhow::Result<VecDeque<T>> {
+    let file = match std::fs::File::open(path) {
+        Ok(x) => x,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Default::default()),
+        Err(err) => return Err(err).with_context(|| format!("opening {}", path.display())),
+    };
+
+    let mut values = VecDeque::with_capacity(limit);
+
+    if limit == 0 {
+        return Ok(values);
+    }
+
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("reading {}", path.display()))?;
+
+        let value = serde_json::from_str::<T>(&line)
+            .with_context(|| format!("parsing {} line {}", path.display(), i + 1))?;
+
+        if values.len() == limit {
+            values.pop_front();
+        }
+
+        values.push_back(value);
+    }
+
+    Ok(values)
+}
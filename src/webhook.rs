@@ -0,0 +1,179 @@
+use crate::{
+    events::{Event, Events},
+    secret::Secret,
+    sleep::sleep_with_cancel,
+    APP_USER_AGENT,
+};
+use anyhow::Context;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// Give up on a delivery after this many tries.
+const WEBHOOK_ATTEMPTS: u32 = 4;
+
+/// Wait this long after the first failure. Doubled after every failure after that.
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Identical events within this window are retries of the same thing and only sent once.
+///
+/// Kept short. Real repeats, like losing the flag to the same player twice, are at least a cooldown apart.
+const WEBHOOK_DEDUP_WINDOW: Duration = Duration::from_secs(5);
+
+/// Where to send events.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// chat webhook urls are secrets
+    pub url: Secret<String>,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+/// The shape of the body that we POST.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The event as JSON with a human readable `message`.
+    #[default]
+    Json,
+    /// `{"content": message}`
+    Discord,
+    /// `{"text": message}`
+    Slack,
+}
+
+impl WebhookFormat {
+    pub fn body(&self, event: &Event) -> serde_json::Value {
+        match self {
+            Self::Json => {
+                let mut body = serde_json::to_value(event).expect("events always serialize");
+                body["message"] = event.message().into();
+                body
+            }
+            Self::Discord => json!({ "content": event.message() }),
+            Self::Slack => json!({ "text": event.message() }),
+        }
+    }
+}
+
+/// A client for webhooks. Unlike the game client, plain http is allowed so that local receivers work.
+pub fn webhook_client() -> anyhow::Result<Client> {
+    Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(10))
+        .user_agent(APP_USER_AGENT)
+        .build()
+        .context("webhook client error")
+}
+
+/// Send every event worth a notification to every webhook until cancelled.
+pub async fn webhook_loop(
+    webhooks: Vec<WebhookConfig>,
+    events: Events,
+    cancellation_token: CancellationToken,
+) -> anyhow::Result<()> {
+    let client = webhook_client()?;
+
+    let webhooks = Arc::new(webhooks);
+
+    let mut events = events.subscribe();
+
+    let mut sent_at = HashMap::<Event, Instant>::new();
+
+    loop {
+        let event = tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            x = events.recv() => match x {
+                Ok(x) => x,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "webhooks fell behind. some events were dropped");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if !event.notify() {
+            continue;
+        }
+
+        let now = Instant::now();
+
+        sent_at.retain(|_, x| now.duration_since(*x) < WEBHOOK_DEDUP_WINDOW);
+
+        if sent_at.contains_key(&event) {
+            debug!(?event, "skipping duplicate webhook");
+            continue;
+        }
+
+        sent_at.insert(event.clone(), now);
+
+        // deliver in the background so that a slow webhook doesn't hold up the others
+        for i in 0..webhooks.len() {
+            let client = client.clone();
+            let webhooks = webhooks.clone();
+            let event = event.clone();
+            let cancellation_token = cancellation_token.clone();
+
+            tokio::spawn(async move {
+                if let Err(err) = send(&client, &webhooks[i], &event, &cancellation_token).await {
+                    warn!(?err, webhook = i, "webhook failed");
+                }
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// POST one event to one webhook. Retries with backoff.
+pub async fn send(
+    client: &Client,
+    webhook: &WebhookConfig,
+    event: &Event,
+    cancellation_token: &CancellationToken,
+) -> anyhow::Result<()> {
+    let body = webhook.format.body(event);
+
+    let mut delay = WEBHOOK_RETRY_DELAY;
+
+    for attempt in 1..=WEBHOOK_ATTEMPTS {
+        let result = client
+            .post(webhook.url.expose())
+            .json(&body)
+            .send()
+            .await
+            .and_then(|x| x.error_for_status())
+            // the url is a secret
+            .map_err(|x| x.without_url());
+
+        match result {
+            Ok(_) => return Ok(()),
+            Err(err) if attempt == WEBHOOK_ATTEMPTS => {
+                return Err(err).context("giving up on webhook");
+            }
+            Err(err) => {
+                debug!(?err, attempt, ?delay, "retrying webhook");
+
+                sleep_with_cancel(cancellation_token, delay).await;
+
+                if cancellation_token.is_cancelled() {
+                    anyhow::bail!("cancelled");
+                }
+
+                delay *= 2;
+            }
+        }
+    }
+
+    unreachable!("the last attempt always returns")
+}
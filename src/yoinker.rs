@@ -1,56 +1,165 @@
 use crate::{
-    sleep::{long_jitter, short_jitter, sleep_with_cancel},
-    strategy::{self, YoinkStrategy},
-    Config, State, COOLDOWN_TIME,
+    admin::LastDecision,
+    audit::AuditRecord,
+    backend::{YoinkBackend, YoinkOutcome},
+    config::StalePolicy,
+    context::BotContext,
+    events::{Event, FAILURE_ALERT_THRESHOLD},
+    schedule::Intensity,
+    scheduler::FireScheduler,
+    sleep::{long_jitter, short_jitter, sleep_short_jitter},
+    strategy::{Decision, YoinkDecision, YoinkStrategy},
+    team::Account,
+    State,
 };
-use anyhow::Context;
-use reqwest::Client;
-use serde_json::json;
-use std::fmt::Debug;
+use chrono::Utc;
+use metrics::counter;
 use std::time::Instant;
-use tokio::{
-    select,
-    sync::mpsc,
-    time::{timeout, Duration},
-};
+use tokio::{select, sync::watch, time::timeout};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, trace, warn};
-use url::Url;
 
-/// Runs the yoink bot until cancelled.
-pub async fn main_loop<const N: usize>(
-    mut app_state_rx: mpsc::UnboundedReceiver<State<N>>,
+/// Runs the yoink bot until cancelled. Runs again every time the state changes or `state_timeout` passes.
+pub async fn main_loop<const N: usize, B: YoinkBackend>(
+    mut state_rx: watch::Receiver<State<N>>,
     cancellation_token: CancellationToken,
-    client: &Client,
-    config: &Config,
+    backend: &B,
+    account: &Account,
+    ctx: &BotContext,
 ) -> anyhow::Result<()> {
-    // TODO: store this in redis so that we can recover from a restart
-    let mut impatient_fire = Instant::now() + COOLDOWN_TIME + COOLDOWN_TIME;
+    let mut scheduler = FireScheduler::new(ctx.clock.clone(), &ctx.data_dir, &account.user_id);
+
+    // give the strategy a couple of cooldowns before getting impatient
+    scheduler.delay_impatience(ctx.tunables().cooldown() * 2);
+
+    let mut last_intensity = None;
+    let mut was_stale = false;
 
     loop {
-        let state = select! {
-            // TODO: what should this timeout be?
-            x = timeout(Duration::from_secs(3), app_state_rx.recv()) => {
-                match x {
-                    Ok(Some(state)) => state,
-                    Ok(None) => {
-                        break;
-                    }
-                    Err(err) => {
-                        warn!(?err, "app_state_rx timeout");
-                        Default::default()
-                    }
+        ctx.control.set_schedule(
+            &account.user_id,
+            scheduler.cooldown_ends_at(),
+            Utc::now()
+                + scheduler
+                    .impatient_at()
+                    .saturating_duration_since(Instant::now().into()),
+        );
+
+        // a snipe waits for our cooldown to end. wake up right then instead of at the next timeout
+        let state_timeout = ctx.tunables().state_timeout();
+
+        let wait = match scheduler.fire_at() {
+            Some(fire_at) => state_timeout.min(fire_at.duration_since(tokio::time::Instant::now())),
+            None => state_timeout,
+        };
+
+        select! {
+            x = timeout(wait, state_rx.changed()) => {
+                // timeouts are normal. the flag doesn't change that often. the latest state is reused
+                if let Ok(Err(_)) = x {
+                    // the stats loop stopped
+                    break;
                 }
             }
+            _ = ctx.control.changed() => {}
             _ = cancellation_token.cancelled() => break,
         };
 
+        let state = state_rx.borrow_and_update().clone();
+
+        if state.stats.is_empty() {
+            warn!("no stats yet");
+        }
+
+        // how long since the stats loop confirmed this state
+        let age = state.checked_at.map(|x| x.elapsed()).unwrap_or_default();
+
+        ctx.control.set_state(&state);
+
+        if ctx.control.take_force_yoink() {
+            let decision = Decision::new(YoinkDecision::Yoink, "the admin api said so");
+
+            record_decision(account, ctx, "admin", &decision);
+
+            let audit = AuditRecord::new(
+                &account.user_id,
+                state.stats.back().map(|x| &**x),
+                &state.diff,
+                "admin",
+                decision,
+            );
+
+            if let Err(err) = yoink_and_sleep(
+                &cancellation_token,
+                backend,
+                account,
+                &mut scheduler,
+                ctx,
+                Instant::now(),
+                audit,
+            )
+            .await
+            {
+                warn!(?err, "forced yoink failed");
+            }
+
+            continue;
+        }
+
+        if ctx.control.is_paused() {
+            trace!("paused");
+            continue;
+        }
+
+        let tunables = ctx.tunables();
+        let staleness = &tunables.staleness;
+
+        let stale = age > staleness.max_age();
+
+        if stale != was_stale {
+            if stale {
+                warn!(?age, policy = ?staleness.policy, "state is stale");
+            } else {
+                info!("state is fresh again");
+            }
+            was_stale = stale;
+        }
+
+        let scheduled = tunables.schedule.intensity_now();
+
+        let intensity = match (stale, staleness.policy) {
+            (false, _) | (true, StalePolicy::Keep) => scheduled,
+            (true, StalePolicy::Conservative) => scheduled.min(Intensity::Passive),
+            (true, StalePolicy::Pause) => {
+                trace!("state is stale. paused");
+                continue;
+            }
+        };
+
+        if last_intensity != Some(intensity) {
+            info!(intensity = intensity.as_str(), stale, "changing intensity");
+            last_intensity = Some(intensity);
+        }
+
+        if intensity == Intensity::Off {
+            trace!("outside of active hours");
+            continue;
+        }
+
+        if scheduler.fire_at().is_some() {
+            // we wake up when our cooldown ends. the holder, the admin, and the strategy all get another say then
+            trace!("waiting for our cooldown");
+            continue;
+        }
+
         if let Err(err) = main(
             state,
+            intensity,
             &cancellation_token,
-            client,
-            config,
-            &mut impatient_fire,
+            backend,
+            account,
+            &mut scheduler,
+            ctx,
         )
         .await
         {
@@ -63,135 +172,549 @@ pub async fn main_loop<const N: usize>(
 
 /// The main logic for the yoink bot.
 /// TODO: instead of watching app_state_rx, maybe this should watch a channel that is updated by strategies? then blue shell and impatient can both be strategies?
-pub async fn main<const N: usize>(
+#[tracing::instrument(skip_all)]
+pub async fn main<const N: usize, B: YoinkBackend>(
     state: State<N>,
+    intensity: Intensity,
     cancellation_token: &CancellationToken,
-    client: &Client,
-    config: &Config,
-    impatient_fire: &mut Instant,
+    backend: &B,
+    account: &Account,
+    scheduler: &mut FireScheduler,
+    ctx: &BotContext,
 ) -> anyhow::Result<()> {
     if let Some(stats) = state.stats.back() {
         let user_times_diff = &state.diff;
 
-        if stats.flag.holder_id == config.user_id {
+        if stats.flag.holder_id == account.user_id {
             // we already have the flag. no need to do anything. don't waste our cooldown timer!
             debug!("we have the flag");
+
+            let decision = Decision::new(YoinkDecision::Wait, "we have the flag");
+
+            ctx.audit.record(&AuditRecord::new(
+                &account.user_id,
+                Some(stats),
+                user_times_diff,
+                "holder",
+                decision,
+            ));
+
+            return Ok(());
+        }
+
+        if account.team.is_teammate(&stats.flag.holder_id) {
+            // never yoink from our own team
+            debug!(holder_id = stats.flag.holder_id, "a teammate has the flag");
+
+            let decision = Decision::new(YoinkDecision::Wait, "a teammate has the flag");
+
+            ctx.audit.record(&AuditRecord::new(
+                &account.user_id,
+                Some(stats),
+                user_times_diff,
+                "team",
+                decision,
+            ));
+
             return Ok(());
         }
 
         // TODO: randomly pick a strategy to use. change on a randomized interval. where should the chosen strategy be stored?
-        // TODO: if no stats, just loop over COOLDOWN_TIME
-        let active_strategy = strategy::RedShellStrategy;
+        // TODO: if no stats, just loop over the cooldown
+        let tunables = ctx.tunables();
+        let active_strategy = tunables.strategy;
 
         // TODO: pass next_fire to this function so that it can alter its strategy based on how long we've been waiting
-        if Instant::now() > *impatient_fire {
+        // passive hours only snipe
+        let may_be_impatient = matches!(intensity, Intensity::Normal | Intensity::Aggressive);
+
+        if may_be_impatient && scheduler.is_impatient() {
             warn!("its been too long! I must yoink!");
-            if yoink_flag_and_sleep(cancellation_token, client, config).await? {
-                // TODO: think about this more
-                *impatient_fire = Instant::now() + long_jitter();
-            } else {
-                // yoinking failed. we got rate limited somehow. just retry soon
-                *impatient_fire = Instant::now() + short_jitter();
-            }
-        } else if active_strategy
-            .should_yoink(cancellation_token, config, stats, user_times_diff)
-            .await?
-        {
-            yoink_flag_and_sleep(cancellation_token, client, config).await?;
 
-            // TODO: save earliest fire time here? we've already slept for the cooldown time so it should be ready. but i'm seeing rate limits
+            let decision = Decision::new(YoinkDecision::Yoink, "waited too long");
+
+            record_decision(account, ctx, "impatient", &decision);
+
+            let audit = AuditRecord::new(
+                &account.user_id,
+                Some(stats),
+                user_times_diff,
+                "impatient",
+                decision,
+            );
+
+            let decided_at = Instant::now();
 
-            *impatient_fire = Instant::now() + long_jitter();
+            match yoink_and_sleep(
+                cancellation_token,
+                backend,
+                account,
+                scheduler,
+                ctx,
+                decided_at,
+                audit,
+            )
+            .await?
+            {
+                None => {
+                    // a teammate beat us to it. check the flag again before doing anything
+                }
+                Some(YoinkOutcome::Yoinked { .. }) => {
+                    // TODO: think about this more
+                    scheduler.delay_impatience(long_jitter(&tunables.timing()));
+                }
+                Some(YoinkOutcome::RateLimited { .. } | YoinkOutcome::Unknown { .. }) => {
+                    // yoinking failed. we got rate limited somehow. just retry soon
+                    scheduler.delay_impatience(short_jitter(&tunables.timing()));
+                }
+            }
         } else {
-            // TODO: include next_fire in a human readable format
-            trace!("not yoinking this time");
+            let decision = active_strategy
+                .should_yoink(
+                    cancellation_token,
+                    ctx,
+                    account,
+                    stats,
+                    user_times_diff,
+                    intensity,
+                )
+                .await?;
+
+            record_decision(account, ctx, active_strategy.as_str(), &decision);
+
+            let mut audit = AuditRecord::new(
+                &account.user_id,
+                Some(stats),
+                user_times_diff,
+                active_strategy.as_str(),
+                decision,
+            );
+
+            match audit.decision.decision {
+                YoinkDecision::Wait => {
+                    // TODO: include next_fire in a human readable format
+                    ctx.audit.record(&audit);
+                    return Ok(());
+                }
+                YoinkDecision::Yoink | YoinkDecision::Snipe => {}
+            }
+
+            if ctx.control.is_paused() {
+                // the strategy can think for a while
+                audit.error = Some("paused before firing".to_string());
+                ctx.audit.record(&audit);
+                return Ok(());
+            }
+
+            let decided_at = Instant::now();
+
+            yoink_and_sleep(
+                cancellation_token,
+                backend,
+                account,
+                scheduler,
+                ctx,
+                decided_at,
+                audit,
+            )
+            .await?;
+
+            scheduler.delay_impatience(long_jitter(&tunables.timing()));
         }
+    } else if matches!(intensity, Intensity::Off | Intensity::Passive) {
+        debug!(
+            intensity = intensity.as_str(),
+            "no stats. not yoinking blind outside of normal hours"
+        );
+    } else if let Some(teammate_id) = account.team.blind_holder() {
+        // the flag is probably still ours. yoinking blind would take it from a teammate
+        debug!(%teammate_id, "one of us took the flag since the last stats. not yoinking blind");
     } else {
         info!("i have no ~~~mouth~~~ stats and i must ~~~scream~~~ yoink");
-        yoink_flag_and_sleep(cancellation_token, client, config).await?;
+
+        let decision = Decision::new(YoinkDecision::Yoink, "no stats");
+
+        record_decision(account, ctx, "no_stats", &decision);
+
+        let audit = AuditRecord::new(&account.user_id, None, &state.diff, "no_stats", decision);
+
+        yoink_and_sleep(
+            cancellation_token,
+            backend,
+            account,
+            scheduler,
+            ctx,
+            Instant::now(),
+            audit,
+        )
+        .await?;
     }
     Ok(())
 }
 
-/// Use [Neynar's](https://neynar.com/) API to yoink the flag. Then sleep for the cooldown period.
+/// Try to yoink the flag with the given backend. The main loop waits out the cooldown that follows.
 ///
-/// TODO: maybe instead of returning a bool, we clear an AtomicBool?
-pub async fn yoink_flag_and_sleep(
+/// Returns `None` without yoinking if a teammate just yoinked. `audit` is written with the outcome.
+#[tracing::instrument(skip_all)]
+pub async fn yoink_and_sleep<B: YoinkBackend>(
     cancellation_token: &CancellationToken,
-    client: &Client,
-    config: &Config,
-) -> anyhow::Result<bool> {
-    // TODO: re-use this. its the same payload every time
-    let payload = json!({
-        "action": {
-            "button": {
-            "index": 1
-            },
-            "frames_url": "https://yoink.terminally.online/",
-            "post_url": "https://yoink.terminally.online/api/yoink"
-        },
-        "cast_hash": config.cast_hash,
-        "signer_uuid": config.nn_signer_uuid
-    });
-
-    let response = client
-        .post("https://api.neynar.com/v2/farcaster/frame/action")
-        .header("api_key", config.nn_api_key.as_str())
-        .json(&payload)
-        .send()
-        .await?;
+    backend: &B,
+    account: &Account,
+    scheduler: &mut FireScheduler,
+    ctx: &BotContext,
+    decided_at: Instant,
+    mut audit: AuditRecord,
+) -> anyhow::Result<Option<YoinkOutcome>> {
+    if !account.team.try_claim_yoink(&account.user_id) {
+        audit.error = Some("a teammate just yoinked".to_string());
+        ctx.audit.record(&audit);
+        return Ok(None);
+    }
+
+    // if the scheduler doesn't have a fire time, we think that our cooldown is over
+    let expect_ready = scheduler.fire_at().is_none();
+
+    let outcome = match backend.yoink(decided_at).await {
+        Ok(x) => x,
+        Err(err) => {
+            account.team.release_yoink(&account.user_id);
 
-    #[allow(dead_code)]
-    #[derive(Debug, serde::Deserialize)]
-    struct Response {
-        version: Option<String>,
-        title: Option<String>,
-        image: Url,
-        // buttons: Vec<serde_json::Value>,
-        // input: serde_json::Value,
-        // state: serde_json::Value,
-        // frames_url: String,
+            counter!("yoinker_yoink_attempts_total", "account" => account.user_id.clone(), "outcome" => "request_failed").increment(1);
+
+            record_failure(account, ctx, format!("{:#}", err));
+
+            audit.error = Some(format!("{:#}", err));
+            ctx.audit.record(&audit);
+
+            return Err(err);
+        }
+    };
+
+    counter!("yoinker_yoink_attempts_total", "account" => account.user_id.clone(), "outcome" => outcome.as_str()).increment(1);
+
+    if let YoinkOutcome::RateLimited { .. } = outcome {
+        counter!("yoinker_rate_limited_total", "account" => account.user_id.clone()).increment(1);
     }
 
-    let response = response.json::<Response>().await?;
+    scheduler.record(&outcome, ctx.tunables().cooldown());
 
-    // inspect the response. the image might be <https://yoink.terminally.online/api/images/ratelimit?date=1721150535550>
-    let (yoinked, duration) = if response.image.path() == "/api/images/ratelimit" {
-        if let Some((_, until)) = response.image.query_pairs().find(|(k, _)| k == "date") {
-            let now_ms = chrono::Utc::now().timestamp_millis();
+    audit.outcome = Some(outcome.clone());
+    ctx.audit.record(&audit);
 
-            // TODO: i don't think this is right. i think the ratelimit is always just giving us the current time
-            // TODO: we also want to mark that we still need to yoink! we need some way to remember to try again
-            let last_yoinked_ms = until.parse::<i64>().context("parsing rate limit date")?;
+    // show the new cooldown right away
+    ctx.control
+        .set_cooldown(&account.user_id, scheduler.cooldown_ends_at());
 
-            // TODO: get the rate limit time from stats or similar
-            let until_ms = last_yoinked_ms + COOLDOWN_TIME.as_millis() as i64;
+    match &outcome {
+        YoinkOutcome::Yoinked { .. } => {
+            ctx.control.reset_failures(&account.user_id);
 
-            if until_ms > now_ms {
-                let duration_ms = (until_ms - now_ms) as u64;
+            account.team.record_yoinked(&account.user_id);
 
-                warn!(duration_ms, "we've been rate limited");
+            ctx.events.emit(Event::Yoinked {
+                account: account.user_id.clone(),
+            });
+        }
+        YoinkOutcome::RateLimited { until } => {
+            ctx.control.reset_failures(&account.user_id);
 
-                (false, Duration::from_millis(duration_ms))
-            } else {
-                anyhow::bail!(
-                    "rate limit date is in the past. {} should be > {}",
-                    until_ms,
-                    now_ms
-                );
+            if expect_ready {
+                ctx.events.emit(Event::RateLimited {
+                    account: account.user_id.clone(),
+                    until: *until,
+                });
             }
-        } else {
-            warn!(?response, "failed to read rate limit query data");
-            (false, COOLDOWN_TIME)
         }
+        YoinkOutcome::Unknown { image } => record_failure(
+            account,
+            ctx,
+            format!("unknown response. image: {:?}", image),
+        ),
+    }
+
+    match &outcome {
+        YoinkOutcome::Yoinked { .. } => {}
+        YoinkOutcome::RateLimited { .. } => {
+            // we didn't take the flag. let a teammate try
+            account.team.release_yoink(&account.user_id);
+        }
+        YoinkOutcome::Unknown { .. } => {
+            // we probably didn't take the flag. let a teammate try and check again soon
+            account.team.release_yoink(&account.user_id);
+
+            sleep_short_jitter(cancellation_token, &ctx.tunables().timing()).await;
+        }
+    }
+
+    Ok(Some(outcome))
+}
+
+/// Count a failed yoink and tell people once it keeps happening.
+fn record_failure(account: &Account, ctx: &BotContext, error: String) {
+    let failures = ctx.control.add_failure(&account.user_id);
+
+    if failures == FAILURE_ALERT_THRESHOLD {
+        ctx.events.emit(Event::RepeatedFailures {
+            account: account.user_id.clone(),
+            failures,
+            error,
+        });
+    }
+}
+
+/// Log and count a decision and show it as the account's latest.
+fn record_decision(
+    account: &Account,
+    ctx: &BotContext,
+    strategy: &'static str,
+    decision: &Decision,
+) {
+    let Decision {
+        decision,
+        reason,
+        targets,
+        rng,
+    } = decision;
+
+    let is_wait = *decision == YoinkDecision::Wait;
+    let decision = decision.as_str();
+
+    // waiting happens constantly. only yoinks are interesting
+    if is_wait {
+        debug!(strategy, decision, reason, ?targets, ?rng, "decided");
     } else {
-        // we've yoinked. the current cooldown timer is 60 seconds. no point in returning before then
-        info!(?response, "yoinked!");
+        info!(strategy, decision, reason, ?targets, ?rng, "decided");
+    }
+
+    counter!("yoinker_decisions_total", "strategy" => strategy, "decision" => decision)
+        .increment(1);
 
-        (true, COOLDOWN_TIME)
+    ctx.control.set_decision(
+        &account.user_id,
+        LastDecision {
+            strategy,
+            decision,
+            at: Utc::now(),
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{FakeBackend, FakeFlag},
+        config::Config,
+        stats::{Stats, StatsFlag},
+        team::test_accounts,
     };
+    use std::{sync::Arc, time::Duration};
+
+    /// In-memory stores with cooldowns saved in a fresh directory.
+    fn test_ctx(name: &str) -> BotContext {
+        let data_dir =
+            std::env::temp_dir().join(format!("yoinker-{}-{}", std::process::id(), name));
+
+        let _ = std::fs::remove_dir_all(&data_dir);
+
+        let config: Config = toml::from_str(&format!(
+            "cast_hash = \"0x1\"\ndata_dir = {:?}",
+            data_dir.display().to_string()
+        ))
+        .unwrap();
+
+        BotContext::in_memory(&config)
+    }
+
+    fn state_held_by(holder_id: &str) -> State<2> {
+        let mut state = State::default();
+
+        state.push_stats(Arc::new(Stats {
+            flag: StatsFlag {
+                holder_id: holder_id.to_string(),
+                ..Default::default()
+            },
+            user_times: [(holder_id.to_string(), 60)].into_iter().collect(),
+            ..Default::default()
+        }));
 
-    sleep_with_cancel(cancellation_token, duration).await;
+        state
+    }
+
+    /// Run `main` once for each account. The token is cancelled so that nobody waits out a cooldown.
+    async fn run_main(
+        ctx: &BotContext,
+        flag: &Arc<FakeFlag>,
+        accounts: &[Account],
+        state: State<2>,
+    ) {
+        let cancellation_token = CancellationToken::new();
+        cancellation_token.cancel();
+
+        for account in accounts {
+            let backend = FakeBackend::new(flag.clone(), account);
+            let mut scheduler =
+                FireScheduler::new(ctx.clock.clone(), &ctx.data_dir, &account.user_id);
+
+            main(
+                state.clone(),
+                Intensity::Normal,
+                &cancellation_token,
+                &backend,
+                account,
+                &mut scheduler,
+                ctx,
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn never_yoinks_from_ourselves_or_a_teammate() {
+        let ctx = test_ctx("teammate");
+        let flag = Arc::new(FakeFlag::new(ctx.tunables().cooldown()));
+        let accounts = test_accounts(&["farcaster:1", "farcaster:2"]);
+
+        run_main(&ctx, &flag, &accounts, state_held_by("farcaster:1")).await;
+        run_main(&ctx, &flag, &accounts, state_held_by("farcaster:2")).await;
+
+        assert_eq!(flag.attempts(), 0);
+    }
 
-    Ok(yoinked)
+    #[tokio::test]
+    async fn teammates_dont_yoink_at_the_same_time() {
+        let ctx = test_ctx("same_time");
+        let flag = Arc::new(FakeFlag::new(ctx.tunables().cooldown()));
+        let accounts = test_accounts(&["farcaster:1", "farcaster:2"]);
+
+        // no stats means yoinking blind
+        run_main(&ctx, &flag, &accounts, State::default()).await;
+
+        assert_eq!(flag.attempts(), 1);
+        assert_eq!(flag.holder_id().as_deref(), Some("farcaster:1"));
+
+        // still blind after the grace window. the flag is probably still ours
+        accounts[0].team.expire_claim();
+
+        run_main(&ctx, &flag, &accounts, State::default()).await;
+
+        assert_eq!(flag.attempts(), 1);
+        assert_eq!(flag.holder_id().as_deref(), Some("farcaster:1"));
+
+        // stats that show the holder
+        accounts[0].team.saw_holder(Instant::now());
+
+        run_main(&ctx, &flag, &accounts, state_held_by("farcaster:1")).await;
+
+        assert_eq!(flag.attempts(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_failed_yoink_lets_a_teammate_try() {
+        let ctx = test_ctx("failed");
+        let flag = Arc::new(FakeFlag::new(ctx.tunables().cooldown()));
+        let accounts = test_accounts(&["farcaster:1", "farcaster:2"]);
+
+        flag.script(YoinkOutcome::RateLimited { until: None });
+
+        run_main(&ctx, &flag, &accounts, State::default()).await;
+
+        assert_eq!(flag.attempts(), 2);
+        assert_eq!(flag.holder_id().as_deref(), Some("farcaster:2"));
+    }
+
+    /// Run the main loop with `state` until `done` or a second passes.
+    async fn run_main_loop(
+        ctx: &BotContext,
+        flag: &Arc<FakeFlag>,
+        account: &Account,
+        state: State<2>,
+        done: impl Fn() -> bool,
+    ) {
+        let cancellation_token = CancellationToken::new();
+        let backend = FakeBackend::new(flag.clone(), account);
+
+        let (state_tx, state_rx) = watch::channel(State::default());
+        state_tx.send(state).unwrap();
+
+        let stop = async {
+            for _ in 0..100 {
+                if done() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            cancellation_token.cancel();
+        };
+
+        let (result, _) = tokio::join!(
+            main_loop(state_rx, cancellation_token.clone(), &backend, account, ctx),
+            stop,
+        );
+
+        result.unwrap();
+    }
+
+    #[tokio::test]
+    async fn paused_loop_doesnt_yoink() {
+        let ctx = test_ctx("paused");
+        let flag = Arc::new(FakeFlag::new(ctx.tunables().cooldown()));
+        let accounts = test_accounts(&["farcaster:1"]);
+
+        ctx.control.set_paused(true);
+
+        run_main_loop(&ctx, &flag, &accounts[0], State::default(), || false).await;
+
+        assert_eq!(flag.attempts(), 0);
+    }
+
+    #[tokio::test]
+    async fn force_yoink_works_while_paused() {
+        let ctx = test_ctx("force");
+        let flag = Arc::new(FakeFlag::new(ctx.tunables().cooldown()));
+        let accounts = test_accounts(&["farcaster:1"]);
+
+        ctx.control.set_paused(true);
+        ctx.control.force_yoink();
+
+        // the admin skips the strategy too
+        run_main_loop(
+            &ctx,
+            &flag,
+            &accounts[0],
+            state_held_by("farcaster:2"),
+            || flag.attempts() > 0,
+        )
+        .await;
+
+        assert_eq!(flag.attempts(), 1);
+        assert_eq!(flag.holder_id().as_deref(), Some("farcaster:1"));
+    }
+
+    #[tokio::test]
+    async fn the_admin_is_heard_during_our_cooldown() {
+        let ctx = test_ctx("heard");
+        let flag = Arc::new(FakeFlag::new(ctx.tunables().cooldown()));
+        let accounts = test_accounts(&["farcaster:1"]);
+
+        ctx.control.force_yoink();
+
+        // force another yoink while the first one's cooldown is still running
+        run_main_loop(
+            &ctx,
+            &flag,
+            &accounts[0],
+            state_held_by("farcaster:2"),
+            || {
+                if flag.attempts() == 1 {
+                    ctx.control.force_yoink();
+                }
+                flag.attempts() > 1
+            },
+        )
+        .await;
+
+        assert_eq!(flag.attempts(), 2);
+    }
 }